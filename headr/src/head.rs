@@ -0,0 +1,234 @@
+use std::io::{self, BufRead, Read, Write};
+
+/// Marks a line shortened by [`Head::max_line_bytes`].
+const ELLIPSIS: &[u8] = "…".as_bytes();
+
+/// Copies the first `num_lines` lines of `reader` to `writer` byte for byte,
+/// so that input which is not UTF-8 passes through unchanged. Returns the
+/// number of lines copied.
+pub fn head_lines<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+    num_lines: u64,
+) -> io::Result<u64> {
+    let mut copied = 0;
+    for line in Head::new(reader, num_lines) {
+        writer.write_all(&line?)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Copies the first `num_bytes` bytes of `reader` to `writer`, returning the
+/// number of bytes copied.
+pub fn head_bytes<R: Read, W: Write>(reader: R, mut writer: W, num_bytes: u64) -> io::Result<u64> {
+    io::copy(&mut reader.take(num_bytes), &mut writer)
+}
+
+/// An iterator over the first lines of a reader, each with its newline if
+/// it had one.
+///
+/// ```
+/// use headr::Head;
+///
+/// let lines: Vec<Vec<u8>> = Head::new(&b"one\ntwo\nthree"[..], 2)
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(lines, [b"one\n".to_vec(), b"two\n".to_vec()]);
+/// ```
+pub struct Head<R> {
+    reader: R,
+    remaining: u64,
+    max_line_bytes: Option<usize>,
+}
+
+impl<R: BufRead> Head<R> {
+    pub fn new(reader: R, num_lines: u64) -> Head<R> {
+        Head {
+            reader,
+            remaining: num_lines,
+            max_line_bytes: None,
+        }
+    }
+
+    /// Shortens lines longer than `max` bytes, without splitting a UTF-8
+    /// character, and marks them with an ellipsis. The rest of a long line
+    /// is skipped rather than read into memory.
+    pub fn max_line_bytes(mut self, max: Option<usize>) -> Head<R> {
+        self.max_line_bytes = max;
+        self
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        let Some(max) = self.max_line_bytes else {
+            return match self.reader.read_until(b'\n', &mut line)? {
+                0 => Ok(None),
+                _ => Ok(Some(line)),
+            };
+        };
+
+        let bytes = read_until_capped(&mut self.reader, &mut line, max)?;
+        if bytes == 0 {
+            return Ok(None);
+        }
+        if bytes > line.len() {
+            let newline = line.last() == Some(&b'\n');
+            if newline {
+                line.pop();
+            }
+            line.truncate(trim_partial_char(&line).len());
+            line.extend_from_slice(ELLIPSIS);
+            if newline {
+                line.push(b'\n');
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+impl<R: BufRead> Iterator for Head<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let line = self.read_line().transpose();
+        if line.is_none() {
+            self.remaining = 0;
+        }
+        line
+    }
+}
+
+/// Like `read_until(b'\n')`, but keeps at most `max` bytes of the line
+/// before its newline and skips the rest without buffering it. Returns the
+/// number of bytes consumed, which exceeds the length of `line` when the
+/// line was cut short.
+fn read_until_capped(file: &mut impl BufRead, line: &mut Vec<u8>, max: usize) -> io::Result<usize> {
+    let mut consumed = 0;
+    loop {
+        let available = file.fill_buf()?;
+        if available.is_empty() {
+            return Ok(consumed);
+        }
+        let (used, done) = match available.iter().position(|&byte| byte == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (available.len(), false),
+        };
+        let content = if done { used - 1 } else { used };
+        let room = max.saturating_sub(line.len());
+        line.extend_from_slice(&available[..content.min(room)]);
+        if done {
+            line.push(b'\n');
+        }
+        file.consume(used);
+        consumed += used;
+        if done {
+            return Ok(consumed);
+        }
+    }
+}
+
+/// Drops a UTF-8 sequence left incomplete at the end of `bytes`, so that a
+/// shortened line does not end in half a character.
+fn trim_partial_char(bytes: &[u8]) -> &[u8] {
+    let tail = bytes.len().saturating_sub(4);
+    match bytes[tail..].iter().rposition(|byte| byte & 0xc0 != 0x80) {
+        Some(index) => match std::str::from_utf8(&bytes[tail + index..]) {
+            Err(error) if error.error_len().is_none() => &bytes[..tail + index],
+            _ => bytes,
+        },
+        None => bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{head_bytes, head_lines, trim_partial_char, Head};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_head_lines() {
+        let mut out = Vec::new();
+        let copied = head_lines(Cursor::new("one\ntwo\r\nthree\n"), &mut out, 2).unwrap();
+        assert_eq!(copied, 2);
+        assert_eq!(out, b"one\ntwo\r\n");
+
+        // Fewer lines than asked for, the last without a newline
+        let mut out = Vec::new();
+        let copied = head_lines(Cursor::new("one\ntwo"), &mut out, 10).unwrap();
+        assert_eq!(copied, 2);
+        assert_eq!(out, b"one\ntwo");
+
+        let mut out = Vec::new();
+        assert_eq!(head_lines(Cursor::new(""), &mut out, 10).unwrap(), 0);
+        assert_eq!(head_lines(Cursor::new("one\n"), &mut out, 0).unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_head_lines_binary() {
+        let input = b"\xff\xfe\n\x00\xc3\n";
+        let mut out = Vec::new();
+        head_lines(Cursor::new(input), &mut out, 1).unwrap();
+        assert_eq!(out, b"\xff\xfe\n");
+    }
+
+    #[test]
+    fn test_head_bytes() {
+        let mut out = Vec::new();
+        let copied = head_bytes(Cursor::new("Öne line"), &mut out, 1).unwrap();
+        assert_eq!(copied, 1);
+        // Half of "Ö", left as it is
+        assert_eq!(out, b"\xc3");
+
+        let mut out = Vec::new();
+        assert_eq!(head_bytes(Cursor::new("ab"), &mut out, 10).unwrap(), 2);
+        assert_eq!(out, b"ab");
+    }
+
+    #[test]
+    fn test_head_max_line_bytes() {
+        let input = "short\nrather long\nÖÖÖ\nlong, no newline";
+        let lines: Vec<Vec<u8>> = Head::new(Cursor::new(input), 10)
+            .max_line_bytes(Some(5))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            lines,
+            [
+                b"short\n".to_vec(),
+                "rathe…\n".as_bytes().to_vec(),
+                "ÖÖ…\n".as_bytes().to_vec(),
+                "long,…".as_bytes().to_vec(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_head_stops_after_limit() {
+        let mut head = Head::new(Cursor::new("one\ntwo\n"), 1);
+        assert_eq!(head.next().unwrap().unwrap(), b"one\n");
+        assert!(head.next().is_none());
+
+        let mut head = Head::new(Cursor::new("one\n"), 5);
+        assert_eq!(head.next().unwrap().unwrap(), b"one\n");
+        assert!(head.next().is_none());
+        assert!(head.next().is_none());
+    }
+
+    #[test]
+    fn test_trim_partial_char() {
+        assert_eq!(trim_partial_char(b""), b"");
+        assert_eq!(trim_partial_char(b"abc"), b"abc");
+        assert_eq!(trim_partial_char("aÖ".as_bytes()), "aÖ".as_bytes());
+        // "Ö" cut after its first byte, and "€" after its first two
+        assert_eq!(trim_partial_char(b"a\xc3"), b"a");
+        assert_eq!(trim_partial_char(b"a\xe2\x82"), b"a");
+        // Bytes that are not UTF-8 at all are left alone
+        assert_eq!(trim_partial_char(b"a\xff"), b"a\xff");
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+/// An opened input, kept concrete so that callers can inspect or seek
+/// regular files before reading them.
+pub enum Input {
+    Stdin(io::Stdin),
+    File(File),
+}
+
+impl Input {
+    /// Opens `filename`, treating `-` as standard input.
+    pub fn open(filename: &str) -> io::Result<Input> {
+        match filename {
+            "-" => Ok(Input::Stdin(io::stdin())),
+            _ => Ok(Input::File(File::open(filename)?)),
+        }
+    }
+
+    pub fn into_reader(self) -> Box<dyn BufRead> {
+        match self {
+            Input::Stdin(stdin) => Box::new(BufReader::new(stdin)),
+            Input::File(file) => Box::new(BufReader::new(file)),
+        }
+    }
+}
+
+/// Opens `filename` for buffered reading, treating `-` as standard input.
+pub fn open(filename: &str) -> io::Result<Box<dyn BufRead>> {
+    Input::open(filename).map(Input::into_reader)
+}
+
+/// Formats an I/O error the way coreutils does, without the
+/// " (os error N)" suffix Rust appends.
+pub fn describe(error: &io::Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+/// Writes the `==> name <==` lines that separate files in multi-file
+/// output, with a blank line before every header but the first.
+pub struct Headers {
+    show: bool,
+    first: bool,
+}
+
+impl Headers {
+    pub fn new(show: bool) -> Headers {
+        Headers { show, first: true }
+    }
+
+    pub fn print(&mut self, out: &mut impl Write, filename: &str) -> io::Result<()> {
+        if self.show {
+            let separator = if self.first { "" } else { "\n" };
+            writeln!(out, "{}==> {} <==", separator, filename)?;
+            self.first = false;
+        }
+        Ok(())
+    }
+}
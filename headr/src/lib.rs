@@ -1,74 +1,246 @@
-use clap::{Arg, Command};
+mod head;
+mod input;
+mod preview;
+mod sample;
+mod size;
+
+pub use head::{head_bytes, head_lines, Head};
+pub use input::{describe, open, Headers, Input};
+pub use size::parse_size;
+
+use clap::{parser::ValueSource, Arg, Command};
+use preview::Format;
+use regex::bytes::Regex;
+use sample::Sample;
+use std::collections::BTreeMap;
 use std::convert::From;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufWriter, IsTerminal, LineWriter, Read, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+/// Limits on what is printed of each file. `bytes` caps how much of a file
+/// is read at all, and `lines` how many lines are printed, so that when both
+/// are given output stops at whichever limit is reached first.
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    lines: usize,
-    bytes: Option<usize>,
+    lines: Option<u64>,
+    bytes: Option<u64>,
+    max_line_bytes: Option<usize>,
+    stop: Option<Stop>,
+    sample: Option<Sample>,
+    preview: Option<Format>,
+    jobs: usize,
+}
+
+/// Where to stop printing lines, independent of the `-n` count.
+#[derive(Debug)]
+enum Stop {
+    /// Print lines up to the first one matching the pattern, and that line
+    /// too when `inclusive` is set.
+    Until { pattern: Regex, inclusive: bool },
+    /// Print lines for as long as they match the pattern.
+    While(Regex),
 }
 
-fn parse_positive_int(val: &str) -> MyResult<usize> {
-    match val.parse() {
-        Ok(i) if i > 0 => Ok(i),
-        _ => Err(From::from(val)),
-        // _ => Err(val.into()),
-        // _ => Err(Into::into(val)),
+impl Stop {
+    /// Returns whether to print `line`, and whether to keep reading after it.
+    fn check(&self, line: &[u8]) -> (bool, bool) {
+        let end = line
+            .iter()
+            .rposition(|byte| !matches!(byte, b'\n' | b'\r'))
+            .map_or(0, |index| index + 1);
+        let line = &line[..end];
+        match self {
+            Stop::Until { pattern, inclusive } => {
+                if pattern.is_match(line) {
+                    (*inclusive, false)
+                } else {
+                    (true, true)
+                }
+            }
+            Stop::While(pattern) => {
+                let matched = pattern.is_match(line);
+                (matched, matched)
+            }
+        }
     }
 }
 
-#[test]
-fn test_parse_positive_int() {
-    // 3は正の整数なのでOK
-    let res = parse_positive_int("3");
-    assert!(res.is_ok());
-    assert_eq!(res.unwrap(), 3);
-
-    // 数字でない文字列の場合はエラー
-    let res = parse_positive_int("foo");
-    assert!(res.is_err());
-    assert_eq!(res.unwrap_err().to_string(), "foo".to_string());
-
-    // 0の場合もエラー
-    let res = parse_positive_int("0");
-    assert!(res.is_err());
-    assert_eq!(res.unwrap_err().to_string(), "0".to_string());
+pub fn run(config: Config) -> MyResult<ExitCode> {
+    // A terminal gets each line as soon as it is read, so that input typed
+    // on it is echoed line by line rather than all at the end
+    let stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        print_all(&config, LineWriter::new(stdout))
+    } else {
+        print_all(&config, BufWriter::new(stdout))
+    }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let num_files = config.files.len();
+fn print_all<W: Write>(config: &Config, out: W) -> MyResult<ExitCode> {
+    let mut printer = Printer {
+        out,
+        headers: Headers::new(config.files.len() > 1),
+        failed: false,
+    };
 
-    for (index, filename) in config.files.iter().enumerate() {
-        match open(&filename) {
-            Err(error) => eprintln!("{}: {}", filename, error),
-            Ok(mut file) => {
-                if num_files > 1 {
-                    println!("{}==> {} <==", if index > 0 { "\n" } else { "" }, filename);
+    if config.jobs > 1 {
+        run_parallel(config, &mut printer)?;
+    } else {
+        for filename in &config.files {
+            match open(filename) {
+                Err(error) => printer.cannot_open(filename, &error)?,
+                Ok(file) => {
+                    printer.header(filename)?;
+                    if let Err(error) = print_head(file, filename, config, &mut printer.out) {
+                        printer.error_reading(filename, &error)?;
+                    }
                 }
+            }
+        }
+    }
+    printer.out.flush()?;
 
-                if let Some(num_bytes) = config.bytes {
-                    let mut handle = file.take(num_bytes as u64);
-                    let mut buffer = vec![0; num_bytes];
-                    let bytes_read = handle.read(&mut buffer)?;
-                    print!("{}", String::from_utf8_lossy(&buffer[..bytes_read]));
-                } else {
-                    let mut line = String::new();
-                    for _ in 0..config.lines {
-                        let bytes = file.read_line(&mut line)?;
-                        if bytes == 0 {
-                            break;
+    Ok(if printer.failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Writes everything to a single buffered stdout, flushing it before any
+/// error message so that both streams stay in order on a terminal.
+struct Printer<W: Write> {
+    out: W,
+    headers: Headers,
+    failed: bool,
+}
+
+impl<W: Write> Printer<W> {
+    fn header(&mut self, filename: &str) -> io::Result<()> {
+        self.headers.print(&mut self.out, filename)
+    }
+
+    fn cannot_open(&mut self, filename: &str, error: &io::Error) -> io::Result<()> {
+        self.out.flush()?;
+        eprintln!(
+            "headr: cannot open '{}' for reading: {}",
+            filename,
+            describe(error)
+        );
+        self.failed = true;
+        Ok(())
+    }
+
+    fn error_reading(&mut self, filename: &str, error: &io::Error) -> io::Result<()> {
+        self.out.flush()?;
+        eprintln!("headr: error reading '{}': {}", filename, describe(error));
+        self.failed = true;
+        Ok(())
+    }
+}
+
+/// What a worker found when reading one file ahead of printing it.
+enum Outcome {
+    Unopened(io::Error),
+    Read(Vec<u8>, io::Result<()>),
+}
+
+/// Reads files on `config.jobs` threads, printing each one's output as soon
+/// as every file before it has been printed.
+fn run_parallel<W: Write>(config: &Config, printer: &mut Printer<W>) -> io::Result<()> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..config.jobs.min(config.files.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(filename) = config.files.get(index) else {
+                    break;
+                };
+                let outcome = match open(filename) {
+                    Err(error) => Outcome::Unopened(error),
+                    Ok(file) => {
+                        let mut buffer = Vec::new();
+                        let result = print_head(file, filename, config, &mut buffer);
+                        Outcome::Read(buffer, result)
+                    }
+                };
+                if sender.send((index, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut printed = 0;
+        for (index, outcome) in receiver {
+            pending.insert(index, outcome);
+            while let Some(outcome) = pending.remove(&printed) {
+                let filename = &config.files[printed];
+                match outcome {
+                    Outcome::Unopened(error) => printer.cannot_open(filename, &error)?,
+                    Outcome::Read(buffer, result) => {
+                        printer.header(filename)?;
+                        printer.out.write_all(&buffer)?;
+                        if let Err(error) = result {
+                            printer.error_reading(filename, &error)?;
                         }
-                        print!("{}", line);
-                        line.clear();
                     }
                 }
+                printed += 1;
             }
         }
+        Ok(())
+    })
+}
+
+fn print_head(
+    mut file: Box<dyn BufRead>,
+    filename: &str,
+    config: &Config,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if let (Some(format), Some(num_records)) = (config.preview, config.lines) {
+        // Undetected formats are printed as plain lines.
+        if preview::print_preview(&mut file, filename, format, num_records, out)? {
+            return Ok(());
+        }
+    }
+
+    let mut file = file.take(config.bytes.unwrap_or(u64::MAX));
+    let Some(num_lines) = config.lines else {
+        io::copy(&mut file, out)?;
+        return Ok(());
+    };
+
+    if let Some(sample) = &config.sample {
+        let lines = Head::new(file, u64::MAX).max_line_bytes(config.max_line_bytes);
+        return sample.print(lines, num_lines, out);
+    }
+
+    for line in Head::new(file, num_lines).max_line_bytes(config.max_line_bytes) {
+        let line = line?;
+        let (show, more) = match &config.stop {
+            Some(stop) => stop.check(&line),
+            None => (true, true),
+        };
+        if show {
+            out.write_all(&line)?;
+        }
+        if !more {
+            break;
+        }
     }
     Ok(())
 }
@@ -90,9 +262,9 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("BYTES")
                 .short('c')
                 .long("bytes")
-                .help("Print bytes of each of the specified files.")
+                .help("Print bytes of each of the specified files; with --lines, stop at whichever limit is reached first.")
                 .num_args(1)
-                .value_parser(clap::value_parser!(usize)),
+                .value_parser(parse_size),
         )
         .arg(
             Arg::new("lines")
@@ -102,8 +274,92 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Print count lines of each of the specified files.")
                 .num_args(1)
                 .default_value("10")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("max_line_bytes")
+                .value_name("N")
+                .long("max-line-bytes")
+                .help("Shorten lines longer than N bytes, marking them with an ellipsis.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("until")
+                .value_name("REGEX")
+                .long("until")
+                .help("Print lines up to the first line matching REGEX.")
+                .num_args(1)
+                .conflicts_with("while"),
+        )
+        .arg(
+            Arg::new("include_match")
+                .long("include-match")
+                .help("With --until, also print the matching line.")
+                .num_args(0)
+                .requires("until"),
+        )
+        .arg(
+            Arg::new("while")
+                .value_name("REGEX")
+                .long("while")
+                .help("Print lines for as long as they match REGEX.")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sample")
+                .value_name("N")
+                .long("sample")
+                .help("Print N lines picked at random from the whole input, in their original order.")
+                .num_args(1)
                 .value_parser(clap::value_parser!(usize))
-                .conflicts_with("bytes"),
+                .conflicts_with_all(["bytes", "lines", "until", "while", "every"]),
+        )
+        .arg(
+            Arg::new("seed")
+                .value_name("SEED")
+                .long("seed")
+                .help("Seed the random choice of --sample, to make it reproducible.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64))
+                .requires("sample"),
+        )
+        .arg(
+            Arg::new("every")
+                .value_name("K")
+                .long("every")
+                .help("Print every Kth line, that is lines K, 2K, 3K and so on, up to LINES lines if given.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .conflicts_with_all(["bytes", "until", "while"]),
+        )
+        .arg(
+            Arg::new("preview")
+                .value_name("FORMAT")
+                .long("preview")
+                .help("Show the header and first LINES records of CSV, TSV or JSON Lines data as a table or pretty-printed objects; FORMAT is 'auto' (the default), 'csv', 'tsv' or 'jsonl'.")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("auto")
+                .value_parser(Format::parse)
+                .conflicts_with_all([
+                    "bytes",
+                    "max_line_bytes",
+                    "until",
+                    "while",
+                    "sample",
+                    "every",
+                ]),
+        )
+        .arg(
+            Arg::new("jobs")
+                .value_name("N")
+                .short('j')
+                .long("jobs")
+                .help("Read up to N files at once, still printing them in order.")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(clap::value_parser!(u64).range(1..)),
         )
         .get_matches();
 
@@ -113,19 +369,48 @@ pub fn get_args() -> MyResult<Config> {
         .map(ToOwned::to_owned)
         .collect();
 
-    let lines = matches.get_one::<usize>("lines").copied().unwrap();
-    let bytes = matches.get_one::<usize>("bytes").copied();
+    let bytes = matches.get_one::<u64>("bytes").copied();
+
+    let stop = if let Some(pattern) = matches.get_one::<String>("until") {
+        Some(Stop::Until {
+            pattern: parse_regex(pattern)?,
+            inclusive: matches.get_flag("include_match"),
+        })
+    } else if let Some(pattern) = matches.get_one::<String>("while") {
+        Some(Stop::While(parse_regex(pattern)?))
+    } else {
+        None
+    };
+
+    let sample = if let Some(&size) = matches.get_one::<usize>("sample") {
+        Some(Sample::Random {
+            size,
+            seed: matches.get_one::<u64>("seed").copied(),
+        })
+    } else {
+        matches.get_one::<u64>("every").copied().map(Sample::Every)
+    };
+
+    // A stop pattern, sampling or -c replaces the default line count, while
+    // an explicit -n combines with them.
+    let lines = match matches.value_source("lines") {
+        Some(ValueSource::DefaultValue) if stop.is_some() || sample.is_some() => Some(u64::MAX),
+        Some(ValueSource::DefaultValue) if bytes.is_some() => None,
+        _ => matches.get_one::<u64>("lines").copied(),
+    };
 
     Ok(Config {
         files,
         lines,
         bytes,
+        max_line_bytes: matches.get_one::<usize>("max_line_bytes").copied(),
+        stop,
+        sample,
+        preview: matches.get_one::<Format>("preview").copied(),
+        jobs: matches.get_one::<u64>("jobs").copied().unwrap() as usize,
     })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+fn parse_regex(pattern: &str) -> MyResult<Regex> {
+    Regex::new(pattern).map_err(|_| From::from(format!("Invalid pattern \"{}\"", pattern)))
 }
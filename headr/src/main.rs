@@ -1,6 +1,11 @@
-fn main() {
-    if let Err(error) = headr::get_args().and_then(headr::run) {
-        eprintln!("{}", error);
-        std::process::exit(1);
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match headr::get_args().and_then(headr::run) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
     }
 }
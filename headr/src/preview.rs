@@ -0,0 +1,173 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
+
+/// Formats understood by `--preview`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// Guess from the file name, or failing that from the first line.
+    Auto,
+    Csv,
+    Tsv,
+    Jsonl,
+}
+
+impl Format {
+    pub fn parse(val: &str) -> Result<Format, String> {
+        match val {
+            "auto" => Ok(Format::Auto),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "jsonl" => Ok(Format::Jsonl),
+            _ => Err(format!("unknown format '{}'", val)),
+        }
+    }
+}
+
+/// Resolves `Format::Auto` for one input, returning `None` when it does not
+/// look like any of the supported formats.
+fn detect(filename: &str, file: &mut dyn BufRead) -> io::Result<Option<Format>> {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("csv") => return Ok(Some(Format::Csv)),
+        Some("tsv" | "tab") => return Ok(Some(Format::Tsv)),
+        Some("jsonl" | "ndjson") => return Ok(Some(Format::Jsonl)),
+        _ => {}
+    }
+
+    // Sniff without consuming anything, so that the whole input can still
+    // be printed either way.
+    let buffer = file.fill_buf()?;
+    let first = buffer.split(|&byte| byte == b'\n').next().unwrap_or(&[]);
+    Ok(if first.trim_ascii_start().starts_with(b"{") {
+        Some(Format::Jsonl)
+    } else if first.contains(&b'\t') {
+        Some(Format::Tsv)
+    } else if first.contains(&b',') {
+        Some(Format::Csv)
+    } else {
+        None
+    })
+}
+
+/// Prints the first `num_records` records of `file`: an aligned table under
+/// the header row for CSV and TSV, or pretty-printed objects for JSON
+/// Lines. Returns `false` without reading anything when the format is
+/// `Auto` and could not be detected.
+pub fn print_preview(
+    file: &mut dyn BufRead,
+    filename: &str,
+    format: Format,
+    num_records: u64,
+    out: &mut impl Write,
+) -> io::Result<bool> {
+    let format = match format {
+        Format::Auto => match detect(filename, file)? {
+            Some(format) => format,
+            None => return Ok(false),
+        },
+        format => format,
+    };
+
+    match format {
+        Format::Jsonl => print_jsonl(file, num_records, out)?,
+        Format::Tsv => print_table(file, b'\t', num_records, out)?,
+        _ => print_table(file, b',', num_records, out)?,
+    }
+    Ok(true)
+}
+
+fn print_table(
+    file: &mut dyn BufRead,
+    delimiter: u8,
+    num_records: u64,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        // TSV has no quoting, so a stray quote must not swallow lines.
+        .quoting(delimiter != b'\t')
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut record = csv::ByteRecord::new();
+    // The header row comes on top of the records asked for.
+    while (rows.len() as u64) <= num_records && reader.read_byte_record(&mut record)? {
+        rows.push(record.iter().map(cell).collect());
+    }
+
+    let num_columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![0; num_columns];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        write_row(out, &widths, row)?;
+        if index == 0 {
+            let rules: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+            write_row(out, &widths, &rules)?;
+        }
+    }
+    Ok(())
+}
+
+/// Makes a field printable on one line of the table.
+fn cell(field: &[u8]) -> String {
+    String::from_utf8_lossy(field)
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn write_row(out: &mut impl Write, widths: &[usize], cells: &[String]) -> io::Result<()> {
+    // Pad every cell but the last non-empty one, so that lines carry no
+    // trailing spaces.
+    let used = cells
+        .iter()
+        .rposition(|cell| !cell.is_empty())
+        .map_or(0, |index| index + 1);
+    let cells = &cells[..used];
+    let mut line = String::new();
+    for (column, cell) in cells.iter().enumerate() {
+        if column > 0 {
+            line.push_str("  ");
+        }
+        line.push_str(cell);
+        if column + 1 < cells.len() {
+            let padding = widths[column] - cell.width();
+            line.extend(std::iter::repeat_n(' ', padding));
+        }
+    }
+    writeln!(out, "{}", line)
+}
+
+fn print_jsonl(file: &mut dyn BufRead, num_records: u64, out: &mut impl Write) -> io::Result<()> {
+    let mut printed = 0;
+    for (index, line) in file.split(b'\n').enumerate() {
+        if printed >= num_records {
+            break;
+        }
+        let line = line?;
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        let value: serde_json::Value = serde_json::from_slice(&line).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", index + 1, error),
+            )
+        })?;
+        serde_json::to_writer_pretty(&mut *out, &value)?;
+        writeln!(out)?;
+        printed += 1;
+    }
+    Ok(())
+}
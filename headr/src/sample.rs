@@ -0,0 +1,140 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, Write};
+
+/// Ways of picking lines from the whole of an input rather than its start.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Sample {
+    /// A uniformly random selection of up to `size` lines, reproducible
+    /// when seeded.
+    Random { size: usize, seed: Option<u64> },
+    /// Every `Kth` line, that is lines K, 2K, 3K and so on, counting from 1
+    /// as `awk 'NR % K == 0'` does.
+    Every(u64),
+}
+
+impl Sample {
+    /// Prints the sampled `lines` in their original order, stopping after
+    /// `max_lines` lines when taking every Kth line.
+    pub fn print(
+        &self,
+        lines: impl Iterator<Item = io::Result<Vec<u8>>>,
+        max_lines: u64,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        match *self {
+            Sample::Random { size, seed } => {
+                let mut rng = match seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                };
+                for line in reservoir(lines, size, &mut rng)? {
+                    out.write_all(&line)?;
+                }
+            }
+            Sample::Every(step) => {
+                let mut printed = 0;
+                for (index, line) in lines.enumerate() {
+                    if printed >= max_lines {
+                        break;
+                    }
+                    let line = line?;
+                    if (index as u64 + 1).is_multiple_of(step) {
+                        out.write_all(&line)?;
+                        printed += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Picks `size` lines uniformly at random from a stream of unknown length
+/// in one pass, keeping only the picked lines in memory, and returns them in
+/// their original order.
+fn reservoir(
+    lines: impl Iterator<Item = io::Result<Vec<u8>>>,
+    size: usize,
+    rng: &mut impl Rng,
+) -> io::Result<Vec<Vec<u8>>> {
+    let mut picked: Vec<(usize, Vec<u8>)> = Vec::with_capacity(size.min(1024));
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if picked.len() < size {
+            picked.push((index, line));
+        } else {
+            let slot = rng.gen_range(0..=index);
+            if slot < size {
+                picked[slot] = (index, line);
+            }
+        }
+    }
+    picked.sort_unstable_by_key(|(index, _)| *index);
+    Ok(picked.into_iter().map(|(_, line)| line).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reservoir, Sample};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn numbered(count: usize) -> impl Iterator<Item = std::io::Result<Vec<u8>>> {
+        (0..count).map(|index| Ok(format!("{index}\n").into_bytes()))
+    }
+
+    #[test]
+    fn test_reservoir() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let picked = reservoir(numbered(1000), 10, &mut rng).unwrap();
+        assert_eq!(picked.len(), 10);
+        let indexes: Vec<usize> = picked
+            .iter()
+            .map(|line| String::from_utf8_lossy(line).trim().parse().unwrap())
+            .collect();
+        assert!(indexes.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // The same seed gives the same sample
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(reservoir(numbered(1000), 10, &mut rng).unwrap(), picked);
+
+        // Short inputs are kept whole
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(reservoir(numbered(3), 10, &mut rng).unwrap().len(), 3);
+        assert!(reservoir(numbered(3), 0, &mut rng).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reservoir_is_uniform() {
+        // Each of 10 lines should be picked about half of the time.
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = [0; 10];
+        for _ in 0..2000 {
+            for line in reservoir(numbered(10), 5, &mut rng).unwrap() {
+                let index: usize = String::from_utf8_lossy(&line).trim().parse().unwrap();
+                counts[index] += 1;
+            }
+        }
+        assert!(counts.iter().all(|&count| (850..1150).contains(&count)));
+    }
+
+    #[test]
+    fn test_every() {
+        let mut out = Vec::new();
+        Sample::Every(3)
+            .print(numbered(10), u64::MAX, &mut out)
+            .unwrap();
+        assert_eq!(out, b"2\n5\n8\n");
+
+        let mut out = Vec::new();
+        Sample::Every(3).print(numbered(10), 2, &mut out).unwrap();
+        assert_eq!(out, b"2\n5\n");
+
+        let mut out = Vec::new();
+        Sample::Every(1)
+            .print(numbered(3), u64::MAX, &mut out)
+            .unwrap();
+        assert_eq!(out, b"0\n1\n2\n");
+    }
+}
@@ -0,0 +1,75 @@
+/// Parses a count with an optional GNU-style size suffix, as accepted by
+/// `head -c`/`-n` and `tail -c`/`-n`: `b` (512), `K`/`KiB` (1024), `KB`
+/// (1000), and likewise `M`, `G`, `T`, `P` and `E`.
+pub fn parse_size(val: &str) -> Result<u64, String> {
+    let end = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (digits, suffix) = val.split_at(end);
+
+    match multiplier(suffix) {
+        Some(multiplier) => digits
+            .parse::<u64>()
+            .map_err(|e| e.to_string())?
+            .checked_mul(multiplier)
+            .ok_or_else(|| "number too large".to_string()),
+        // Report the same error a plain number would get.
+        None => val.parse::<u64>().map_err(|e| e.to_string()),
+    }
+}
+
+fn multiplier(suffix: &str) -> Option<u64> {
+    if suffix.is_empty() {
+        return Some(1);
+    }
+    if suffix == "b" {
+        return Some(512);
+    }
+
+    let (unit, base) = match suffix.as_bytes() {
+        [unit] | [unit, b'i', b'B'] => (*unit, 1024u64),
+        [unit, b'B'] => (*unit, 1000),
+        _ => return None,
+    };
+    let power = match unit.to_ascii_uppercase() {
+        b'K' => 1,
+        b'M' => 2,
+        b'G' => 3,
+        b'T' => 4,
+        b'P' => 5,
+        b'E' => 6,
+        _ => return None,
+    };
+    base.checked_pow(power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_size;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("42"), Ok(42));
+        assert_eq!(parse_size("2b"), Ok(1024));
+        assert_eq!(parse_size("1K"), Ok(1024));
+        assert_eq!(parse_size("1k"), Ok(1024));
+        assert_eq!(parse_size("1KiB"), Ok(1024));
+        assert_eq!(parse_size("1KB"), Ok(1000));
+        assert_eq!(parse_size("3M"), Ok(3 * 1024 * 1024));
+        assert_eq!(parse_size("1GB"), Ok(1_000_000_000));
+        assert_eq!(parse_size("1E"), Ok(1 << 60));
+
+        assert_eq!(
+            parse_size("foo"),
+            Err("invalid digit found in string".to_string())
+        );
+        assert_eq!(
+            parse_size("1X"),
+            Err("invalid digit found in string".to_string())
+        );
+        assert_eq!(
+            parse_size("K"),
+            Err("cannot parse integer from empty string".to_string())
+        );
+        assert_eq!(parse_size("16E"), Err("number too large".to_string()));
+    }
+}
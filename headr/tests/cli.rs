@@ -12,6 +12,12 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TWELVE: &str = "./tests/inputs/twelve.txt";
+const EMAIL: &str = "./tests/inputs/email.txt";
+const LONG: &str = "./tests/inputs/long.txt";
+const PEOPLE: &str = "./tests/inputs/people.csv";
+const WORDS: &str = "./tests/inputs/words.tsv";
+const EVENTS: &str = "./tests/inputs/events.jsonl";
+const BAD_JSONL: &str = "./tests/inputs/bad.jsonl";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -69,12 +75,24 @@ fn dies_bad_lines() -> Result<()> {
 
 // --------------------------------------------------
 #[test]
-fn dies_bytes_and_lines() -> Result<()> {
-    let msg = "the argument '--lines <LINES>' cannot be \
-               used with '--bytes <BYTES>'";
+fn dies_bad_pattern() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--until", "(", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid pattern "(""#));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_until_and_while() -> Result<()> {
+    let msg = "the argument '--until <REGEX>' cannot be \
+               used with '--while <REGEX>'";
 
     Command::cargo_bin(PRG)?
-        .args(["-n", "1", "-c", "2"])
+        .args(["--until", "a", "--while", "b"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(msg));
@@ -82,15 +100,46 @@ fn dies_bytes_and_lines() -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn dies_include_match_without_until() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--include-match", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--until <REGEX>"));
+
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn skips_bad_file() -> Result<()> {
     let bad = gen_bad_file();
-    let expected = format!("{bad}: .* [(]os error 2[)]");
+    let expected = format!(
+        "headr: cannot open '{bad}' for reading: \
+        No such file or directory\n"
+    );
     Command::cargo_bin(PRG)?
         .args([EMPTY, &bad, ONE])
         .assert()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .stdout(predicate::str::contains("==> ./tests/inputs/one.txt <=="))
+        .stderr(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_directory() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["./tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("==> ./tests/inputs <=="))
+        .stdout(predicate::str::contains("==> ./tests/inputs/one.txt <=="))
+        .stderr("headr: error reading './tests/inputs': Is a directory\n");
 
     Ok(())
 }
@@ -419,3 +468,432 @@ fn multiple_files_c4() -> Result<()> {
         "tests/expected/all.c4.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn email_until() -> Result<()> {
+    run(
+        &["--until", "^$", EMAIL],
+        "tests/expected/email.txt.until.out",
+    )
+}
+
+#[test]
+fn email_until_include_match() -> Result<()> {
+    run(
+        &["--until", "^$", "--include-match", EMAIL],
+        "tests/expected/email.txt.until.incl.out",
+    )
+}
+
+#[test]
+fn email_until_stdin() -> Result<()> {
+    run_stdin(
+        &["--until", "^$"],
+        EMAIL,
+        "tests/expected/email.txt.until.out",
+    )
+}
+
+#[test]
+fn twelve_until_n3() -> Result<()> {
+    run(
+        &["--until", "seven", "-n", "3", TWELVE],
+        "tests/expected/twelve.txt.until.n3.out",
+    )
+}
+
+#[test]
+fn twelve_while() -> Result<()> {
+    run(
+        &["--while", "o", TWELVE],
+        "tests/expected/twelve.txt.while.out",
+    )
+}
+
+#[test]
+fn multiple_files_until() -> Result<()> {
+    run(
+        &["--until", "^t", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.until.out",
+    )
+}
+
+#[test]
+fn multiple_files_while() -> Result<()> {
+    run(
+        &["--while", "^[a-z]", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.while.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn one_c1_raw_bytes() -> Result<()> {
+    // Only the first byte of "Ö", passed through rather than replaced
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1", ONE])
+        .assert()
+        .success()
+        .stdout(vec![0xc3]);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files_jobs() -> Result<()> {
+    run(
+        &["-j", "3", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.out",
+    )
+}
+
+#[test]
+fn multiple_files_jobs_n2() -> Result<()> {
+    run(
+        &["-j", "8", "-n", "2", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n2.out",
+    )
+}
+
+#[test]
+fn multiple_files_jobs_c4() -> Result<()> {
+    run(
+        &["-j", "2", "-c", "4", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.c4.out",
+    )
+}
+
+#[test]
+fn multiple_files_jobs_until() -> Result<()> {
+    run(
+        &["-j", "4", "--until", "^t", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.until.out",
+    )
+}
+
+#[test]
+fn jobs_skips_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = format!(
+        "headr: cannot open '{bad}' for reading: \
+        No such file or directory\n\
+        headr: error reading './tests/inputs': Is a directory\n"
+    );
+    Command::cargo_bin(PRG)?
+        .args(["-j", "4", "-n", "1", TWO, &bad, "./tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(
+            "==> ./tests/inputs/two.txt <==\nTwo lines.\n\n\
+            ==> ./tests/inputs <==\n\n\
+            ==> ./tests/inputs/one.txt <==\nÖne line, four words.\n",
+        )
+        .stderr(expected);
+
+    Ok(())
+}
+
+#[test]
+fn dies_bad_jobs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid value '0' for '--jobs <N>'",
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn twelve_n3_c10() -> Result<()> {
+    run(
+        &[TWELVE, "-n", "3", "-c", "10"],
+        "tests/expected/twelve.txt.n3.c10.out",
+    )
+}
+
+#[test]
+fn twelve_n2_c100() -> Result<()> {
+    run(
+        &[TWELVE, "-n", "2", "-c", "100"],
+        "tests/expected/twelve.txt.n2.c100.out",
+    )
+}
+
+#[test]
+fn twelve_n3_c10_stdin() -> Result<()> {
+    run_stdin(
+        &["-n", "3", "-c", "10"],
+        TWELVE,
+        "tests/expected/twelve.txt.n3.c10.out",
+    )
+}
+
+#[test]
+fn multiple_files_n1_c2() -> Result<()> {
+    run(
+        &["-n", "1", "-c", "2", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n1.c2.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn long_max_line_bytes() -> Result<()> {
+    run(
+        &["--max-line-bytes", "5", LONG],
+        "tests/expected/long.txt.m5.out",
+    )
+}
+
+#[test]
+fn long_max_line_bytes_n2_c50() -> Result<()> {
+    run(
+        &["--max-line-bytes", "5", "-n", "2", "-c", "50", LONG],
+        "tests/expected/long.txt.m5.n2.c50.out",
+    )
+}
+
+#[test]
+fn long_max_line_bytes_stdin() -> Result<()> {
+    run_stdin(
+        &["--max-line-bytes", "5"],
+        LONG,
+        "tests/expected/long.txt.m5.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_preview_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--preview=xml", PEOPLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown format 'xml'"));
+
+    Ok(())
+}
+
+#[test]
+fn dies_preview_and_bytes() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--preview", "-c", "1", PEOPLE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn preview_csv() -> Result<()> {
+    run(
+        &["--preview", PEOPLE],
+        "tests/expected/people.csv.preview.out",
+    )
+}
+
+#[test]
+fn preview_csv_n2() -> Result<()> {
+    run(
+        &["--preview", "-n", "2", PEOPLE],
+        "tests/expected/people.csv.preview.n2.out",
+    )
+}
+
+#[test]
+fn preview_csv_stdin() -> Result<()> {
+    // Detected from the contents, as there is no file name to go by
+    run_stdin(
+        &["--preview"],
+        PEOPLE,
+        "tests/expected/people.csv.preview.out",
+    )
+}
+
+#[test]
+fn preview_tsv() -> Result<()> {
+    run(
+        &["--preview", WORDS],
+        "tests/expected/words.tsv.preview.out",
+    )
+}
+
+#[test]
+fn preview_jsonl() -> Result<()> {
+    run(
+        &["--preview", EVENTS],
+        "tests/expected/events.jsonl.preview.out",
+    )
+}
+
+#[test]
+fn preview_forced_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--preview=tsv", "-n", "1", TWELVE])
+        .assert()
+        .success()
+        .stdout("one\n---\ntwo\n");
+
+    Ok(())
+}
+
+#[test]
+fn preview_aligns_wide_characters() -> Result<()> {
+    // Columns line up by display width, two for each of these characters
+    Command::cargo_bin(PRG)?
+        .args(["--preview=csv"])
+        .write_stdin("city,code\n東京,13\nOslo,03\n")
+        .assert()
+        .success()
+        .stdout("city  code\n----  ----\n東京  13\nOslo  03\n");
+
+    Ok(())
+}
+
+#[test]
+fn preview_multiple_files_n1() -> Result<()> {
+    // Plain text falls back to printing lines
+    run(
+        &["--preview", "-n", "1", PEOPLE, EVENTS, TWELVE],
+        "tests/expected/all.preview.n1.out",
+    )
+}
+
+#[test]
+fn preview_bad_jsonl() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--preview", BAD_JSONL])
+        .assert()
+        .failure()
+        .stdout("{\n  \"ok\": 1\n}\n")
+        .stderr(predicate::str::starts_with(format!(
+            "headr: error reading '{BAD_JSONL}': line 2: "
+        )));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn sample(args: &[&str]) -> Result<String> {
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[test]
+fn sample_seeded() -> Result<()> {
+    let first = sample(&["--sample", "4", "--seed", "42", TWELVE])?;
+    let second = sample(&["--sample", "4", "--seed", "42", TWELVE])?;
+    assert_eq!(first, second);
+
+    // Four distinct lines of the input, still in their original order
+    let input = fs::read_to_string(TWELVE)?;
+    let all: Vec<&str> = input.lines().collect();
+    let picked: Vec<usize> = first
+        .lines()
+        .map(|line| all.iter().position(|l| *l == line).unwrap())
+        .collect();
+    assert_eq!(picked.len(), 4);
+    assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+
+    Ok(())
+}
+
+#[test]
+fn sample_seeded_stdin() -> Result<()> {
+    let expected = sample(&["--sample", "4", "--seed", "7", TWELVE])?;
+    Command::cargo_bin(PRG)?
+        .args(["--sample", "4", "--seed", "7"])
+        .write_stdin(fs::read_to_string(TWELVE)?)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    Ok(())
+}
+
+#[test]
+fn sample_more_than_input() -> Result<()> {
+    // All twelve lines, in order, whatever the seed
+    let output = sample(&["--sample", "20", TWELVE])?;
+    assert_eq!(output, fs::read_to_string(TWELVE)?);
+
+    Ok(())
+}
+
+#[test]
+fn dies_sample_and_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--sample", "2", "-n", "3", TWELVE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--sample <N>' cannot be used with '--lines <LINES>'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dies_seed_without_sample() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--seed", "1", TWELVE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--sample <N>"));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn twelve_every5() -> Result<()> {
+    run(
+        &["--every", "5", TWELVE],
+        "tests/expected/twelve.txt.every5.out",
+    )
+}
+
+#[test]
+fn twelve_every5_n2() -> Result<()> {
+    run(
+        &["--every", "5", "-n", "2", TWELVE],
+        "tests/expected/twelve.txt.every5.n2.out",
+    )
+}
+
+#[test]
+fn twelve_every5_stdin() -> Result<()> {
+    run_stdin(
+        &["--every", "5"],
+        TWELVE,
+        "tests/expected/twelve.txt.every5.out",
+    )
+}
+
+#[test]
+fn multiple_files_every2() -> Result<()> {
+    run(
+        &["--every", "2", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.every2.out",
+    )
+}
+
+#[test]
+fn every_counts_lines_from_one() -> Result<()> {
+    // Lines K, 2K, 3K and so on, as awk 'NR % K == 0' picks them
+    Command::cargo_bin(PRG)?
+        .args(["--every", "3", TWELVE])
+        .assert()
+        .success()
+        .stdout("three\nsix\nnine\ntwelve\n");
+
+    Ok(())
+}
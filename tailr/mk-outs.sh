@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+
+set -u
+
+ROOT="tests/inputs"
+OUT_DIR="tests/expected"
+
+[[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
+
+EMPTY="$ROOT/empty.txt"
+ONE="$ROOT/one.txt"
+TWO="$ROOT/two.txt"
+THREE="$ROOT/three.txt"
+TWELVE="$ROOT/twelve.txt"
+ALL="$EMPTY $ONE $TWO $THREE $TWELVE"
+
+for FILE in $ALL; do
+    BASENAME=$(basename "$FILE")
+    tail        $FILE > ${OUT_DIR}/${BASENAME}.out
+    tail -n 0   $FILE > ${OUT_DIR}/${BASENAME}.n0.out
+    tail -n 1   $FILE > ${OUT_DIR}/${BASENAME}.n1.out
+    tail -n 3   $FILE > ${OUT_DIR}/${BASENAME}.n3.out
+    tail -n +0  $FILE > ${OUT_DIR}/${BASENAME}.n+0.out
+    tail -n +3  $FILE > ${OUT_DIR}/${BASENAME}.n+3.out
+    tail -c 3   $FILE > ${OUT_DIR}/${BASENAME}.c3.out
+    tail -c +8  $FILE > ${OUT_DIR}/${BASENAME}.c+8.out
+done
+
+tail     $ALL > $OUT_DIR/all.out
+tail -q  $ALL > $OUT_DIR/all.q.out
+tail -n 1 $ALL > $OUT_DIR/all.n1.out
+tail -n +3 $ALL > $OUT_DIR/all.n+3.out
+tail -c 3 $ALL > $OUT_DIR/all.c3.out
+tail -v $ONE > $OUT_DIR/one.txt.v.out
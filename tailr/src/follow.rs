@@ -0,0 +1,266 @@
+use crate::Config;
+use headr::{describe, Headers};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::MetadataExt;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Follow {
+    /// Keep reading the file that was opened, even after it is renamed.
+    Descriptor,
+    /// Keep reading whatever file has the name, reopening it when it is
+    /// rotated.
+    Name,
+}
+
+/// A file being followed, which may be missing while following by name.
+pub struct Followed {
+    name: String,
+    file: Option<File>,
+}
+
+impl Followed {
+    pub fn new(name: &str, file: Option<File>) -> Followed {
+        Followed {
+            name: name.to_string(),
+            file,
+        }
+    }
+
+    /// Reads whatever was written since the last call, starting over when
+    /// a regular file has been truncated. Anything else, such as a FIFO, is
+    /// read without blocking for as much as it has.
+    fn read_new(&mut self) -> io::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        if let Some(file) = &mut self.file {
+            let metadata = file.metadata()?;
+            if metadata.is_file() && metadata.len() < file.stream_position()? {
+                eprintln!("tailr: {}: file truncated", self.name);
+                file.seek(SeekFrom::Start(0))?;
+            }
+            match file.read_to_end(&mut buffer) {
+                Err(error) if error.kind() != ErrorKind::WouldBlock => return Err(error),
+                _ => {}
+            }
+        }
+        Ok(buffer)
+    }
+
+    /// Reopens the file when its name now refers to a different file, or
+    /// drops it when the name has gone away.
+    fn check_name(&mut self) {
+        match fs::metadata(&self.name) {
+            Err(error) => {
+                if self.file.take().is_some() {
+                    eprintln!(
+                        "tailr: '{}' has become inaccessible: {}",
+                        self.name,
+                        describe(&error)
+                    );
+                }
+            }
+            Ok(metadata) => {
+                let message = match &self.file {
+                    Some(file) if same_file(file, &metadata) => return,
+                    Some(_) => "has been replaced",
+                    None => "has appeared",
+                };
+                match File::open(&self.name) {
+                    Ok(file) => {
+                        eprintln!("tailr: '{}' {};  following new file", self.name, message);
+                        self.file = Some(file);
+                    }
+                    Err(error) => {
+                        if self.file.take().is_some() {
+                            eprintln!(
+                                "tailr: '{}' has become inaccessible: {}",
+                                self.name,
+                                describe(&error)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn same_file(file: &File, metadata: &fs::Metadata) -> bool {
+    file.metadata()
+        .map(|open| open.dev() == metadata.dev() && open.ino() == metadata.ino())
+        .unwrap_or(false)
+}
+
+/// Prints data appended to `files` until interrupted, or until the process
+/// given with `--pid` exits. `last` is the index of the file whose output
+/// was printed most recently, so that a header is only repeated when the
+/// output switches to another file.
+pub fn follow(
+    mut files: Vec<Followed>,
+    mut last: Option<usize>,
+    headers: &mut Headers,
+    config: &Config,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut watcher = Watcher::new(&files);
+
+    loop {
+        // Check before reading, so that everything the process wrote
+        // before exiting still gets printed.
+        let alive = config.pid.is_none_or(is_alive);
+
+        for (index, entry) in files.iter_mut().enumerate() {
+            let mut buffer = entry.read_new()?;
+            // With --retry, a file followed by descriptor is still waited
+            // for until it can first be opened.
+            if config.follow == Some(Follow::Name) || (config.retry && entry.file.is_none()) {
+                entry.check_name();
+                buffer.extend(entry.read_new()?);
+            }
+
+            if !buffer.is_empty() {
+                if last != Some(index) {
+                    headers.print(out, &entry.name)?;
+                    last = Some(index);
+                }
+                out.write_all(&buffer)?;
+                out.flush()?;
+            }
+        }
+
+        if !alive {
+            return Ok(());
+        }
+        if !config.retry && files.iter().all(|entry| entry.file.is_none()) {
+            eprintln!("tailr: no files remaining");
+            return Ok(());
+        }
+        watcher.wait(config.sleep_interval);
+    }
+}
+
+/// Makes reads of a file that is not regular, such as a FIFO, return what
+/// there is rather than wait for more, so that following it does not hold
+/// up the other files.
+pub fn set_nonblocking(file: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    // SAFETY: `fd` is open for as long as `file` is borrowed, and F_GETFL and
+    // F_SETFL only read and set its status flags.
+    let result = unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 {
+            flags
+        } else {
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK)
+        }
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn is_alive(pid: libc::pid_t) -> bool {
+    // Signal 0 only checks whether the process exists; EPERM means it does
+    // but belongs to someone else.
+    // SAFETY: kill with signal 0 sends nothing and touches no memory, and
+    // `pid` is positive, so it names one process rather than a group.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Waits for followed files to change, using inotify where available and
+/// falling back to sleeping for the interval otherwise.
+struct Watcher {
+    #[cfg(target_os = "linux")]
+    inotify: Option<std::os::fd::OwnedFd>,
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    fn new(files: &[Followed]) -> Watcher {
+        use std::ffi::CString;
+        use std::os::fd::{FromRawFd, OwnedFd};
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::Path;
+
+        // SAFETY: inotify_init1 takes only flags and returns a new
+        // descriptor or -1.
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Watcher { inotify: None };
+        }
+        // SAFETY: `fd` was just opened above and nothing else owns it.
+        let inotify = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // Watching the directories as well notices files being created,
+        // renamed or replaced, which the watch on a file itself cannot.
+        let file_mask =
+            libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+        let dir_mask = libc::IN_MODIFY
+            | libc::IN_ATTRIB
+            | libc::IN_CREATE
+            | libc::IN_DELETE
+            | libc::IN_MOVED_FROM
+            | libc::IN_MOVED_TO;
+        for entry in files {
+            let path = Path::new(&entry.name);
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            for (path, mask) in [(path, file_mask), (dir, dir_mask)] {
+                if let Ok(path) = CString::new(path.as_os_str().as_bytes()) {
+                    // A failed watch only means waiting for the interval.
+                    // SAFETY: `fd` is the open inotify descriptor and `path`
+                    // a NUL-terminated string that outlives the call.
+                    unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) };
+                }
+            }
+        }
+
+        Watcher {
+            inotify: Some(inotify),
+        }
+    }
+
+    fn wait(&mut self, interval: Duration) {
+        use std::os::fd::AsRawFd;
+
+        let Some(inotify) = &self.inotify else {
+            thread::sleep(interval);
+            return;
+        };
+
+        let fd = inotify.as_raw_fd();
+        let mut poll = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = interval.as_millis().min(i32::MAX as u128) as i32;
+        // SAFETY: `poll` points to exactly the one pollfd passed as the count.
+        if unsafe { libc::poll(&mut poll, 1, timeout) } > 0 {
+            // Only the wakeup matters; every file is checked afterwards.
+            let mut events = [0u8; 4096];
+            // SAFETY: the buffer is writable for the length passed, and the
+            // descriptor is non-blocking, so the loop ends once it is empty.
+            while unsafe { libc::read(fd, events.as_mut_ptr().cast(), events.len()) } > 0 {}
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    fn new(_files: &[Followed]) -> Watcher {
+        Watcher {}
+    }
+
+    fn wait(&mut self, interval: Duration) {
+        thread::sleep(interval);
+    }
+}
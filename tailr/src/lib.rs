@@ -0,0 +1,411 @@
+mod follow;
+
+use clap::{Arg, Command};
+use follow::{Follow, Followed};
+use headr::{describe, parse_size, Headers, Input};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::process::ExitCode;
+use std::time::Duration;
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+/// Size of the blocks read backwards when looking for the last lines of a
+/// regular file.
+const BLOCK_SIZE: usize = 8192;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Count {
+    /// The last N lines or bytes.
+    Last(u64),
+    /// Everything from the Nth line or byte on, counting from 1.
+    From(u64),
+}
+
+#[derive(Debug)]
+pub struct Config {
+    files: Vec<String>,
+    lines: Count,
+    bytes: Option<Count>,
+    quiet: bool,
+    verbose: bool,
+    follow: Option<Follow>,
+    retry: bool,
+    pid: Option<libc::pid_t>,
+    sleep_interval: Duration,
+}
+
+fn parse_count(val: &str) -> Result<Count, String> {
+    match val.strip_prefix('+') {
+        Some(rest) => parse_size(rest).map(Count::From),
+        None => parse_size(val.strip_prefix('-').unwrap_or(val)).map(Count::Last),
+    }
+}
+
+fn parse_seconds(val: &str) -> Result<Duration, String> {
+    val.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("invalid number of seconds: '{}'", val))
+}
+
+pub fn run(config: Config) -> MyResult<ExitCode> {
+    let show_headers = config.verbose || (!config.quiet && config.files.len() > 1);
+    let mut headers = Headers::new(show_headers);
+    let mut failed = false;
+    let mut out = io::stdout().lock();
+    let mut followed = Vec::new();
+    let mut last = None;
+
+    match config.follow {
+        None if config.retry => {
+            eprintln!("tailr: warning: --retry ignored; --retry is useful only when following")
+        }
+        Some(Follow::Descriptor) if config.retry => {
+            eprintln!("tailr: warning: --retry only effective for the initial open")
+        }
+        _ => {}
+    }
+
+    for filename in &config.files {
+        let mut file = None;
+        match Input::open(filename) {
+            Err(error) => {
+                eprintln!(
+                    "tailr: cannot open '{}' for reading: {}",
+                    filename,
+                    describe(&error)
+                );
+                failed = true;
+            }
+            Ok(input) => {
+                headers.print(&mut out, filename)?;
+                last = Some(followed.len());
+
+                match print_tail(input, &config, &mut out) {
+                    Ok(tailed) => file = tailed,
+                    Err(error) => {
+                        eprintln!("tailr: error reading '{}': {}", filename, describe(&error));
+                        failed = true;
+                    }
+                }
+            }
+        }
+        // Standard input is not followed, as it cannot grow once read to
+        // the end.
+        if filename != "-" {
+            followed.push(Followed::new(filename, file));
+        }
+    }
+
+    if config.follow.is_some() {
+        out.flush()?;
+        follow::follow(followed, last, &mut headers, &config, &mut out)?;
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Prints the tail of `input`, handing back files positioned at their end
+/// so that they can be followed. A file that is not regular, such as a FIFO,
+/// is read to its end like standard input, then with `-f` read without
+/// blocking for whatever is written to it later.
+fn print_tail(input: Input, config: &Config, out: &mut impl Write) -> io::Result<Option<File>> {
+    match input {
+        Input::File(mut file) if file.metadata()?.is_file() => {
+            tail_file(&mut file, config, out)?;
+            Ok(Some(file))
+        }
+        Input::File(file) if !file.metadata()?.is_dir() => {
+            tail_stream(BufReader::new(&file), config, out)?;
+            if config.follow.is_none() {
+                return Ok(None);
+            }
+            follow::set_nonblocking(&file)?;
+            Ok(Some(file))
+        }
+        input => tail_stream(input.into_reader(), config, out).map(|_| None),
+    }
+}
+
+/// Tails a regular file by seeking straight to the wanted offset.
+fn tail_file(file: &mut File, config: &Config, out: &mut impl Write) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    let start = match (config.bytes, config.lines) {
+        (Some(Count::Last(num_bytes)), _) => len.saturating_sub(num_bytes),
+        (Some(Count::From(num_bytes)), _) => num_bytes.saturating_sub(1),
+        (None, Count::Last(num_lines)) => last_lines_offset(file, len, num_lines)?,
+        (None, Count::From(_)) => return tail_stream(BufReader::new(file), config, out),
+    };
+
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(file, out)?;
+    Ok(())
+}
+
+/// Finds where the last `num_lines` lines of a file of `len` bytes begin
+/// by reading it backwards in blocks.
+fn last_lines_offset(file: &mut File, len: u64, num_lines: u64) -> io::Result<u64> {
+    if num_lines == 0 {
+        return Ok(len);
+    }
+
+    let mut buffer = vec![0; BLOCK_SIZE];
+    let mut remaining = num_lines;
+    let mut end = len;
+
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE as u64);
+        let block = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(block)?;
+
+        for (index, &byte) in block.iter().enumerate().rev() {
+            let offset = start + index as u64 + 1;
+            // A newline in the last byte ends the final line rather than
+            // starting a new one.
+            if byte == b'\n' && offset < len {
+                remaining -= 1;
+                if remaining == 0 {
+                    return Ok(offset);
+                }
+            }
+        }
+        end = start;
+    }
+
+    Ok(0)
+}
+
+/// Tails an input that cannot seek, keeping only what may still be printed
+/// in a bounded ring buffer.
+fn tail_stream(mut file: impl BufRead, config: &Config, out: &mut impl Write) -> io::Result<()> {
+    match (config.bytes, config.lines) {
+        (Some(Count::Last(num_bytes)), _) => {
+            let mut ring = VecDeque::new();
+            let mut buffer = vec![0; BLOCK_SIZE];
+            loop {
+                let bytes_read = file.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
+                }
+                ring.extend(&buffer[..bytes_read]);
+                let excess = ring.len().saturating_sub(num_bytes as usize);
+                ring.drain(..excess);
+            }
+            let (front, back) = ring.as_slices();
+            out.write_all(front)?;
+            out.write_all(back)?;
+        }
+        (Some(Count::From(num_bytes)), _) => {
+            io::copy(
+                &mut file.by_ref().take(num_bytes.saturating_sub(1)),
+                &mut io::sink(),
+            )?;
+            io::copy(&mut file, out)?;
+        }
+        (None, Count::Last(num_lines)) => {
+            let mut ring = VecDeque::new();
+            loop {
+                let mut line = Vec::new();
+                if file.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+                ring.push_back(line);
+                if ring.len() as u64 > num_lines {
+                    ring.pop_front();
+                }
+            }
+            for line in ring {
+                out.write_all(&line)?;
+            }
+        }
+        (None, Count::From(num_lines)) => {
+            let mut line = Vec::new();
+            for _ in 1..num_lines {
+                line.clear();
+                if file.read_until(b'\n', &mut line)? == 0 {
+                    break;
+                }
+            }
+            io::copy(&mut file, out)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn get_args() -> MyResult<Config> {
+    let matches = Command::new("tailr")
+        .version("0.1.0")
+        .author("Ken Youens-Clark <kyclark@gmail.com>")
+        .about("Rust tail")
+        .arg(
+            Arg::new("files")
+                .value_name("FILE")
+                .help("Input file(s)")
+                .num_args(0..)
+                .default_value("-"),
+        )
+        .arg(
+            Arg::new("bytes")
+                .value_name("BYTES")
+                .short('c')
+                .long("bytes")
+                .help("Print the last BYTES bytes, or from byte BYTES on with a leading '+'.")
+                .num_args(1)
+                .allow_hyphen_values(true)
+                .value_parser(parse_count),
+        )
+        .arg(
+            Arg::new("lines")
+                .value_name("LINES")
+                .short('n')
+                .long("lines")
+                .help("Print the last LINES lines, or from line LINES on with a leading '+'.")
+                .num_args(1)
+                .default_value("10")
+                .allow_hyphen_values(true)
+                .value_parser(parse_count)
+                .conflicts_with("bytes"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .visible_alias("silent")
+                .help("Never print headers giving file names.")
+                .num_args(0)
+                .conflicts_with("verbose"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Always print headers giving file names.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("follow")
+                .value_name("HOW")
+                .short('f')
+                .long("follow")
+                .help("Output appended data as the file grows; HOW is 'descriptor' (the default) or 'name'.")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("descriptor")
+                .value_parser(["descriptor", "name"]),
+        )
+        .arg(
+            Arg::new("follow_name")
+                .short('F')
+                .help("Same as --follow=name --retry.")
+                .num_args(0)
+                .conflicts_with("follow"),
+        )
+        .arg(
+            Arg::new("retry")
+                .long("retry")
+                .help("Keep trying to open a file if it is inaccessible.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("pid")
+                .value_name("PID")
+                .long("pid")
+                .help("With -f, terminate after process PID dies.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(i32).range(1..)),
+        )
+        .arg(
+            Arg::new("sleep_interval")
+                .value_name("N")
+                .short('s')
+                .long("sleep-interval")
+                .help("With -f, check for changes at least every N seconds.")
+                .num_args(1)
+                .default_value("1.0")
+                .value_parser(parse_seconds),
+        )
+        .get_matches();
+
+    let files = matches
+        .get_many::<String>("files")
+        .unwrap()
+        .map(ToOwned::to_owned)
+        .collect();
+
+    let follow_name = matches.get_flag("follow_name");
+    let follow = match matches.get_one::<String>("follow").map(String::as_str) {
+        _ if follow_name => Some(Follow::Name),
+        Some("name") => Some(Follow::Name),
+        Some(_) => Some(Follow::Descriptor),
+        None => None,
+    };
+
+    Ok(Config {
+        files,
+        lines: matches.get_one::<Count>("lines").copied().unwrap(),
+        bytes: matches.get_one::<Count>("bytes").copied(),
+        quiet: matches.get_flag("quiet"),
+        verbose: matches.get_flag("verbose"),
+        follow,
+        retry: follow_name || matches.get_flag("retry"),
+        pid: matches.get_one::<libc::pid_t>("pid").copied(),
+        sleep_interval: matches
+            .get_one::<Duration>("sleep_interval")
+            .copied()
+            .unwrap(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_lines_offset, parse_count, Count, BLOCK_SIZE};
+    use std::io::{Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_parse_count() {
+        assert_eq!(parse_count("3"), Ok(Count::Last(3)));
+        assert_eq!(parse_count("-3"), Ok(Count::Last(3)));
+        assert_eq!(parse_count("+3"), Ok(Count::From(3)));
+        assert_eq!(parse_count("+0"), Ok(Count::From(0)));
+        assert_eq!(parse_count("2K"), Ok(Count::Last(2048)));
+        assert_eq!(parse_count("+1KB"), Ok(Count::From(1000)));
+        assert!(parse_count("foo").is_err());
+        assert!(parse_count("+-3").is_err());
+    }
+
+    #[test]
+    fn test_last_lines_offset() {
+        let mut file = tempfile::tempfile().unwrap();
+        // Lines of ten bytes, so that line boundaries fall across blocks.
+        let num_lines = 3 * BLOCK_SIZE / 10;
+        for index in 0..num_lines {
+            writeln!(file, "{:09}", index).unwrap();
+        }
+        let len = (num_lines * 10) as u64;
+
+        assert_eq!(last_lines_offset(&mut file, len, 0).unwrap(), len);
+        assert_eq!(last_lines_offset(&mut file, len, 1).unwrap(), len - 10);
+        assert_eq!(
+            last_lines_offset(&mut file, len, 1000).unwrap(),
+            len - 10_000
+        );
+        assert_eq!(
+            last_lines_offset(&mut file, len, num_lines as u64).unwrap(),
+            0
+        );
+        assert_eq!(last_lines_offset(&mut file, len, u64::MAX).unwrap(), 0);
+
+        // Without a trailing newline the partial line counts as the last.
+        file.seek(SeekFrom::End(0)).unwrap();
+        write!(file, "partial").unwrap();
+        assert_eq!(last_lines_offset(&mut file, len + 7, 1).unwrap(), len);
+    }
+}
@@ -0,0 +1,992 @@
+use anyhow::Result;
+use assert_cmd::{cargo::cargo_bin, Command};
+use predicates::prelude::*;
+use pretty_assertions::assert_eq;
+use rand::{distributions::Alphanumeric, Rng};
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::path::Path;
+use std::process::{self, Child, Stdio};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const PRG: &str = "tailr";
+const EMPTY: &str = "tests/inputs/empty.txt";
+const ONE: &str = "tests/inputs/one.txt";
+const TWO: &str = "tests/inputs/two.txt";
+const THREE: &str = "tests/inputs/three.txt";
+const TWELVE: &str = "tests/inputs/twelve.txt";
+
+// --------------------------------------------------
+fn random_string() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(7)
+        .map(char::from)
+        .collect()
+}
+
+// --------------------------------------------------
+fn gen_bad_file() -> String {
+    loop {
+        let filename = random_string();
+        if fs::metadata(&filename).is_err() {
+            return filename;
+        }
+    }
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_bytes() -> Result<()> {
+    let bad = random_string();
+    let expected = format!(
+        "invalid value '{bad}' for \
+        '--bytes <BYTES>': invalid digit found in string"
+    );
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", &bad, EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_lines() -> Result<()> {
+    let bad = random_string();
+    let expected = format!(
+        "error: invalid value '{bad}' for \
+        '--lines <LINES>': invalid digit found in string"
+    );
+    Command::cargo_bin(PRG)?
+        .args(["-n", &bad, EMPTY])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bytes_and_lines() -> Result<()> {
+    let msg = "the argument '--lines <LINES>' cannot be \
+               used with '--bytes <BYTES>'";
+
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1", "-c", "2"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(msg));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_quiet_and_verbose() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-q", "-v", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--quiet' cannot be used with '--verbose'",
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = format!(
+        "tailr: cannot open '{bad}' for reading: \
+        No such file or directory\n"
+    );
+    Command::cargo_bin(PRG)?
+        .args([EMPTY, &bad, ONE])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("==> tests/inputs/one.txt <=="))
+        .stderr(expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_directory() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", ONE])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("==> tests/inputs <=="))
+        .stdout(predicate::str::contains("==> tests/inputs/one.txt <=="))
+        .stderr("tailr: error reading 'tests/inputs': Is a directory\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run(args: &[&str], expected_file: &str) -> Result<()> {
+    // Extra work here due to lossy UTF
+    let mut file = File::open(expected_file)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let expected = String::from_utf8_lossy(&buffer);
+
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> Result<()> {
+    // Extra work here due to lossy UTF
+    let mut file = File::open(expected_file)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    let expected = String::from_utf8_lossy(&buffer);
+    let input = fs::read_to_string(input_file)?;
+
+    let output = Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .args(args)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn empty() -> Result<()> {
+    run(&[EMPTY], "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn empty_n0() -> Result<()> {
+    run(&["-n", "0", EMPTY], "tests/expected/empty.txt.n0.out")
+}
+
+#[test]
+fn empty_n1() -> Result<()> {
+    run(&["-n", "1", EMPTY], "tests/expected/empty.txt.n1.out")
+}
+
+#[test]
+fn empty_n3() -> Result<()> {
+    run(&["-n", "3", EMPTY], "tests/expected/empty.txt.n3.out")
+}
+
+#[test]
+fn empty_n_plus_0() -> Result<()> {
+    run(&["-n", "+0", EMPTY], "tests/expected/empty.txt.n+0.out")
+}
+
+#[test]
+fn empty_n_plus_3() -> Result<()> {
+    run(&["-n", "+3", EMPTY], "tests/expected/empty.txt.n+3.out")
+}
+
+#[test]
+fn empty_c3() -> Result<()> {
+    run(&["-c", "3", EMPTY], "tests/expected/empty.txt.c3.out")
+}
+
+#[test]
+fn empty_c_plus_8() -> Result<()> {
+    run(&["-c", "+8", EMPTY], "tests/expected/empty.txt.c+8.out")
+}
+
+#[test]
+fn empty_stdin() -> Result<()> {
+    run_stdin(&[], EMPTY, "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn empty_n0_stdin() -> Result<()> {
+    run_stdin(&["-n", "0"], EMPTY, "tests/expected/empty.txt.n0.out")
+}
+
+#[test]
+fn empty_n1_stdin() -> Result<()> {
+    run_stdin(&["-n", "1"], EMPTY, "tests/expected/empty.txt.n1.out")
+}
+
+#[test]
+fn empty_n3_stdin() -> Result<()> {
+    run_stdin(&["-n", "3"], EMPTY, "tests/expected/empty.txt.n3.out")
+}
+
+#[test]
+fn empty_n_plus_0_stdin() -> Result<()> {
+    run_stdin(&["-n", "+0"], EMPTY, "tests/expected/empty.txt.n+0.out")
+}
+
+#[test]
+fn empty_n_plus_3_stdin() -> Result<()> {
+    run_stdin(&["-n", "+3"], EMPTY, "tests/expected/empty.txt.n+3.out")
+}
+
+#[test]
+fn empty_c3_stdin() -> Result<()> {
+    run_stdin(&["-c", "3"], EMPTY, "tests/expected/empty.txt.c3.out")
+}
+
+#[test]
+fn empty_c_plus_8_stdin() -> Result<()> {
+    run_stdin(&["-c", "+8"], EMPTY, "tests/expected/empty.txt.c+8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn one() -> Result<()> {
+    run(&[ONE], "tests/expected/one.txt.out")
+}
+
+#[test]
+fn one_n0() -> Result<()> {
+    run(&["-n", "0", ONE], "tests/expected/one.txt.n0.out")
+}
+
+#[test]
+fn one_n1() -> Result<()> {
+    run(&["-n", "1", ONE], "tests/expected/one.txt.n1.out")
+}
+
+#[test]
+fn one_n3() -> Result<()> {
+    run(&["-n", "3", ONE], "tests/expected/one.txt.n3.out")
+}
+
+#[test]
+fn one_n_plus_0() -> Result<()> {
+    run(&["-n", "+0", ONE], "tests/expected/one.txt.n+0.out")
+}
+
+#[test]
+fn one_n_plus_3() -> Result<()> {
+    run(&["-n", "+3", ONE], "tests/expected/one.txt.n+3.out")
+}
+
+#[test]
+fn one_c3() -> Result<()> {
+    run(&["-c", "3", ONE], "tests/expected/one.txt.c3.out")
+}
+
+#[test]
+fn one_c_plus_8() -> Result<()> {
+    run(&["-c", "+8", ONE], "tests/expected/one.txt.c+8.out")
+}
+
+#[test]
+fn one_stdin() -> Result<()> {
+    run_stdin(&[], ONE, "tests/expected/one.txt.out")
+}
+
+#[test]
+fn one_n0_stdin() -> Result<()> {
+    run_stdin(&["-n", "0"], ONE, "tests/expected/one.txt.n0.out")
+}
+
+#[test]
+fn one_n1_stdin() -> Result<()> {
+    run_stdin(&["-n", "1"], ONE, "tests/expected/one.txt.n1.out")
+}
+
+#[test]
+fn one_n3_stdin() -> Result<()> {
+    run_stdin(&["-n", "3"], ONE, "tests/expected/one.txt.n3.out")
+}
+
+#[test]
+fn one_n_plus_0_stdin() -> Result<()> {
+    run_stdin(&["-n", "+0"], ONE, "tests/expected/one.txt.n+0.out")
+}
+
+#[test]
+fn one_n_plus_3_stdin() -> Result<()> {
+    run_stdin(&["-n", "+3"], ONE, "tests/expected/one.txt.n+3.out")
+}
+
+#[test]
+fn one_c3_stdin() -> Result<()> {
+    run_stdin(&["-c", "3"], ONE, "tests/expected/one.txt.c3.out")
+}
+
+#[test]
+fn one_c_plus_8_stdin() -> Result<()> {
+    run_stdin(&["-c", "+8"], ONE, "tests/expected/one.txt.c+8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn two() -> Result<()> {
+    run(&[TWO], "tests/expected/two.txt.out")
+}
+
+#[test]
+fn two_n0() -> Result<()> {
+    run(&["-n", "0", TWO], "tests/expected/two.txt.n0.out")
+}
+
+#[test]
+fn two_n1() -> Result<()> {
+    run(&["-n", "1", TWO], "tests/expected/two.txt.n1.out")
+}
+
+#[test]
+fn two_n3() -> Result<()> {
+    run(&["-n", "3", TWO], "tests/expected/two.txt.n3.out")
+}
+
+#[test]
+fn two_n_plus_0() -> Result<()> {
+    run(&["-n", "+0", TWO], "tests/expected/two.txt.n+0.out")
+}
+
+#[test]
+fn two_n_plus_3() -> Result<()> {
+    run(&["-n", "+3", TWO], "tests/expected/two.txt.n+3.out")
+}
+
+#[test]
+fn two_c3() -> Result<()> {
+    run(&["-c", "3", TWO], "tests/expected/two.txt.c3.out")
+}
+
+#[test]
+fn two_c_plus_8() -> Result<()> {
+    run(&["-c", "+8", TWO], "tests/expected/two.txt.c+8.out")
+}
+
+#[test]
+fn two_stdin() -> Result<()> {
+    run_stdin(&[], TWO, "tests/expected/two.txt.out")
+}
+
+#[test]
+fn two_n0_stdin() -> Result<()> {
+    run_stdin(&["-n", "0"], TWO, "tests/expected/two.txt.n0.out")
+}
+
+#[test]
+fn two_n1_stdin() -> Result<()> {
+    run_stdin(&["-n", "1"], TWO, "tests/expected/two.txt.n1.out")
+}
+
+#[test]
+fn two_n3_stdin() -> Result<()> {
+    run_stdin(&["-n", "3"], TWO, "tests/expected/two.txt.n3.out")
+}
+
+#[test]
+fn two_n_plus_0_stdin() -> Result<()> {
+    run_stdin(&["-n", "+0"], TWO, "tests/expected/two.txt.n+0.out")
+}
+
+#[test]
+fn two_n_plus_3_stdin() -> Result<()> {
+    run_stdin(&["-n", "+3"], TWO, "tests/expected/two.txt.n+3.out")
+}
+
+#[test]
+fn two_c3_stdin() -> Result<()> {
+    run_stdin(&["-c", "3"], TWO, "tests/expected/two.txt.c3.out")
+}
+
+#[test]
+fn two_c_plus_8_stdin() -> Result<()> {
+    run_stdin(&["-c", "+8"], TWO, "tests/expected/two.txt.c+8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn three() -> Result<()> {
+    run(&[THREE], "tests/expected/three.txt.out")
+}
+
+#[test]
+fn three_n0() -> Result<()> {
+    run(&["-n", "0", THREE], "tests/expected/three.txt.n0.out")
+}
+
+#[test]
+fn three_n1() -> Result<()> {
+    run(&["-n", "1", THREE], "tests/expected/three.txt.n1.out")
+}
+
+#[test]
+fn three_n3() -> Result<()> {
+    run(&["-n", "3", THREE], "tests/expected/three.txt.n3.out")
+}
+
+#[test]
+fn three_n_plus_0() -> Result<()> {
+    run(&["-n", "+0", THREE], "tests/expected/three.txt.n+0.out")
+}
+
+#[test]
+fn three_n_plus_3() -> Result<()> {
+    run(&["-n", "+3", THREE], "tests/expected/three.txt.n+3.out")
+}
+
+#[test]
+fn three_c3() -> Result<()> {
+    run(&["-c", "3", THREE], "tests/expected/three.txt.c3.out")
+}
+
+#[test]
+fn three_c_plus_8() -> Result<()> {
+    run(&["-c", "+8", THREE], "tests/expected/three.txt.c+8.out")
+}
+
+#[test]
+fn three_stdin() -> Result<()> {
+    run_stdin(&[], THREE, "tests/expected/three.txt.out")
+}
+
+#[test]
+fn three_n0_stdin() -> Result<()> {
+    run_stdin(&["-n", "0"], THREE, "tests/expected/three.txt.n0.out")
+}
+
+#[test]
+fn three_n1_stdin() -> Result<()> {
+    run_stdin(&["-n", "1"], THREE, "tests/expected/three.txt.n1.out")
+}
+
+#[test]
+fn three_n3_stdin() -> Result<()> {
+    run_stdin(&["-n", "3"], THREE, "tests/expected/three.txt.n3.out")
+}
+
+#[test]
+fn three_n_plus_0_stdin() -> Result<()> {
+    run_stdin(&["-n", "+0"], THREE, "tests/expected/three.txt.n+0.out")
+}
+
+#[test]
+fn three_n_plus_3_stdin() -> Result<()> {
+    run_stdin(&["-n", "+3"], THREE, "tests/expected/three.txt.n+3.out")
+}
+
+#[test]
+fn three_c3_stdin() -> Result<()> {
+    run_stdin(&["-c", "3"], THREE, "tests/expected/three.txt.c3.out")
+}
+
+#[test]
+fn three_c_plus_8_stdin() -> Result<()> {
+    run_stdin(&["-c", "+8"], THREE, "tests/expected/three.txt.c+8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn twelve() -> Result<()> {
+    run(&[TWELVE], "tests/expected/twelve.txt.out")
+}
+
+#[test]
+fn twelve_n0() -> Result<()> {
+    run(&["-n", "0", TWELVE], "tests/expected/twelve.txt.n0.out")
+}
+
+#[test]
+fn twelve_n1() -> Result<()> {
+    run(&["-n", "1", TWELVE], "tests/expected/twelve.txt.n1.out")
+}
+
+#[test]
+fn twelve_n3() -> Result<()> {
+    run(&["-n", "3", TWELVE], "tests/expected/twelve.txt.n3.out")
+}
+
+#[test]
+fn twelve_n_plus_0() -> Result<()> {
+    run(&["-n", "+0", TWELVE], "tests/expected/twelve.txt.n+0.out")
+}
+
+#[test]
+fn twelve_n_plus_3() -> Result<()> {
+    run(&["-n", "+3", TWELVE], "tests/expected/twelve.txt.n+3.out")
+}
+
+#[test]
+fn twelve_c3() -> Result<()> {
+    run(&["-c", "3", TWELVE], "tests/expected/twelve.txt.c3.out")
+}
+
+#[test]
+fn twelve_c_plus_8() -> Result<()> {
+    run(&["-c", "+8", TWELVE], "tests/expected/twelve.txt.c+8.out")
+}
+
+#[test]
+fn twelve_stdin() -> Result<()> {
+    run_stdin(&[], TWELVE, "tests/expected/twelve.txt.out")
+}
+
+#[test]
+fn twelve_n0_stdin() -> Result<()> {
+    run_stdin(&["-n", "0"], TWELVE, "tests/expected/twelve.txt.n0.out")
+}
+
+#[test]
+fn twelve_n1_stdin() -> Result<()> {
+    run_stdin(&["-n", "1"], TWELVE, "tests/expected/twelve.txt.n1.out")
+}
+
+#[test]
+fn twelve_n3_stdin() -> Result<()> {
+    run_stdin(&["-n", "3"], TWELVE, "tests/expected/twelve.txt.n3.out")
+}
+
+#[test]
+fn twelve_n_plus_0_stdin() -> Result<()> {
+    run_stdin(&["-n", "+0"], TWELVE, "tests/expected/twelve.txt.n+0.out")
+}
+
+#[test]
+fn twelve_n_plus_3_stdin() -> Result<()> {
+    run_stdin(&["-n", "+3"], TWELVE, "tests/expected/twelve.txt.n+3.out")
+}
+
+#[test]
+fn twelve_c3_stdin() -> Result<()> {
+    run_stdin(&["-c", "3"], TWELVE, "tests/expected/twelve.txt.c3.out")
+}
+
+#[test]
+fn twelve_c_plus_8_stdin() -> Result<()> {
+    run_stdin(&["-c", "+8"], TWELVE, "tests/expected/twelve.txt.c+8.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn one_verbose() -> Result<()> {
+    run(&["-v", ONE], "tests/expected/one.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn multiple_files() -> Result<()> {
+    run(&[EMPTY, ONE, TWO, THREE, TWELVE], "tests/expected/all.out")
+}
+
+#[test]
+fn multiple_files_quiet() -> Result<()> {
+    run(
+        &["-q", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.q.out",
+    )
+}
+
+#[test]
+fn multiple_files_n1() -> Result<()> {
+    run(
+        &["-n", "1", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n1.out",
+    )
+}
+
+#[test]
+fn multiple_files_n_plus_3() -> Result<()> {
+    run(
+        &["-n", "+3", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.n+3.out",
+    )
+}
+
+#[test]
+fn multiple_files_c3() -> Result<()> {
+    run(
+        &["-c", "3", EMPTY, ONE, TWO, THREE, TWELVE],
+        "tests/expected/all.c3.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn large_file() -> Result<()> {
+    // Spans many blocks, so that both the backwards search on the file and
+    // the ring buffer on stdin have to carry lines across block boundaries.
+    let lines: Vec<String> = (0..20_000).map(|i| format!("line {i}\n")).collect();
+    let input = lines.concat();
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("large.txt");
+    fs::write(&path, &input)?;
+    let path = path.to_str().unwrap();
+
+    let expected = lines[15_000..].concat();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "5000", path])
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    Command::cargo_bin(PRG)?
+        .args(["-n", "5000"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let expected = &input[input.len() - 12_345..];
+    Command::cargo_bin(PRG)?
+        .args(["-c", "12345", path])
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Command::cargo_bin(PRG)?
+        .args(["-c", "12345"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_sleep_interval() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-s", "soon", ONE])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "invalid number of seconds: 'soon'",
+        ));
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_pid() -> Result<()> {
+    // One too large for a pid_t would wrap to a negative pid, and kill(-1, 0)
+    // always succeeds; 0 is no process at all
+    for pid in ["4294967295", "0"] {
+        Command::cargo_bin(PRG)?
+            .args(["-f", "--pid", pid, ONE])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "invalid value '{}' for '--pid <PID>'",
+                pid
+            )));
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+/// A `tailr` running in the background, with its output collected as it
+/// arrives.
+struct Follower {
+    child: Child,
+    stdout: Arc<Output>,
+    stderr: Arc<Output>,
+}
+
+impl Follower {
+    fn spawn(args: &[&str]) -> Result<Follower> {
+        let mut child = process::Command::new(cargo_bin(PRG))
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdout = collect(child.stdout.take().unwrap());
+        let stderr = collect(child.stderr.take().unwrap());
+        Ok(Follower {
+            child,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Waits for stdout to read `expected` exactly.
+    fn expect_stdout(&self, expected: &str) {
+        wait_for(&self.stdout, expected, |output| output == expected);
+    }
+
+    /// Waits for stderr to contain `expected`.
+    fn expect_stderr(&self, expected: &str) {
+        wait_for(&self.stderr, expected, |output| output.contains(expected));
+    }
+}
+
+impl Drop for Follower {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// What a child has written to a pipe so far, and whether the pipe has
+/// closed, with a condition variable signalled whenever either changes.
+#[derive(Default)]
+struct Output {
+    state: Mutex<(Vec<u8>, bool)>,
+    changed: Condvar,
+}
+
+/// How long a wait may go on before the test is failed as hung. Waits end as
+/// soon as the output they wait for arrives, so this is only a backstop.
+const HANG_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn collect(mut pipe: impl Read + Send + 'static) -> Arc<Output> {
+    let output = Arc::new(Output::default());
+    let shared = Arc::clone(&output);
+    thread::spawn(move || {
+        let mut buffer = [0; 1024];
+        loop {
+            let bytes_read = pipe.read(&mut buffer).unwrap_or(0);
+            let mut state = shared.state.lock().unwrap();
+            if bytes_read == 0 {
+                state.1 = true;
+            } else {
+                state.0.extend(&buffer[..bytes_read]);
+            }
+            shared.changed.notify_all();
+            if bytes_read == 0 {
+                return;
+            }
+        }
+    });
+    output
+}
+
+/// Waits until the output is `done`, failing if the pipe closes first.
+fn wait_for(output: &Output, expected: &str, done: impl Fn(&str) -> bool) {
+    let state = output.state.lock().unwrap();
+    let (state, _) = output
+        .changed
+        .wait_timeout_while(state, HANG_TIMEOUT, |(bytes, closed)| {
+            !*closed && !done(&String::from_utf8_lossy(bytes))
+        })
+        .unwrap();
+    let current = String::from_utf8_lossy(&state.0);
+    if !done(&current) {
+        assert_eq!(current, expected);
+        panic!("output ended without {expected:?}");
+    }
+}
+
+fn append(path: &Path, text: &str) -> Result<()> {
+    OpenOptions::new()
+        .append(true)
+        .open(path)?
+        .write_all(text.as_bytes())?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_appended() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    fs::write(&path, "one\ntwo\n")?;
+
+    let tailr = Follower::spawn(&["-f", "-n", "1", "-s", "0.1", path.to_str().unwrap()])?;
+    tailr.expect_stdout("two\n");
+    append(&path, "three\n")?;
+    tailr.expect_stdout("two\nthree\n");
+
+    // Following the descriptor keeps reading the file after a rename.
+    let renamed = dir.path().join("log.1");
+    fs::rename(&path, &renamed)?;
+    append(&renamed, "four\n")?;
+    tailr.expect_stdout("two\nthree\nfour\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_truncated() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    fs::write(&path, "one\ntwo\n")?;
+    let name = path.to_str().unwrap();
+
+    let tailr = Follower::spawn(&["-f", "-s", "0.1", name])?;
+    tailr.expect_stdout("one\ntwo\n");
+    File::create(&path)?;
+    tailr.expect_stderr(&format!("tailr: {name}: file truncated"));
+    append(&path, "three\n")?;
+    tailr.expect_stdout("one\ntwo\nthree\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_name_rotated() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    fs::write(&path, "one\n")?;
+    let name = path.to_str().unwrap();
+
+    let tailr = Follower::spawn(&["-F", "-s", "0.1", name])?;
+    tailr.expect_stdout("one\n");
+
+    // Replace the file in one step, the way log rotation does.
+    let next = dir.path().join("log.next");
+    fs::write(&next, "two\n")?;
+    fs::rename(&next, &path)?;
+    tailr.expect_stderr(&format!(
+        "tailr: '{name}' has been replaced;  following new file"
+    ));
+    tailr.expect_stdout("one\ntwo\n");
+
+    fs::remove_file(&path)?;
+    tailr.expect_stderr(&format!(
+        "tailr: '{name}' has become inaccessible: No such file or directory"
+    ));
+    fs::write(&path, "three\n")?;
+    tailr.expect_stderr(&format!(
+        "tailr: '{name}' has appeared;  following new file"
+    ));
+    tailr.expect_stdout("one\ntwo\nthree\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_name_missing_at_start() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    let name = path.to_str().unwrap();
+
+    let tailr = Follower::spawn(&["-F", "-s", "0.1", name])?;
+    tailr.expect_stderr(&format!(
+        "tailr: cannot open '{name}' for reading: No such file or directory"
+    ));
+    fs::write(&path, "one\n")?;
+    tailr.expect_stdout("one\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_multiple_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let first = dir.path().join("first");
+    let second = dir.path().join("second");
+    fs::write(&first, "a\n")?;
+    fs::write(&second, "b\n")?;
+    let (name1, name2) = (first.to_str().unwrap(), second.to_str().unwrap());
+
+    let tailr = Follower::spawn(&["-f", "-s", "0.1", name1, name2])?;
+    let mut expected = format!("==> {name1} <==\na\n\n==> {name2} <==\nb\n");
+    tailr.expect_stdout(&expected);
+
+    // The header is repeated only when output switches to another file.
+    append(&second, "c\n")?;
+    expected.push_str("c\n");
+    tailr.expect_stdout(&expected);
+    append(&first, "d\n")?;
+    expected.push_str(&format!("\n==> {name1} <==\nd\n"));
+    tailr.expect_stdout(&expected);
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_until_pid_exits() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    fs::write(&path, "one\n")?;
+
+    // A writer that lives until its standard input is closed
+    let mut writer = process::Command::new("cat")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let pid = writer.id().to_string();
+    let mut tailr = Follower::spawn(&["-f", "--pid", &pid, "-s", "0.1", path.to_str().unwrap()])?;
+    tailr.expect_stdout("one\n");
+
+    append(&path, "two\n")?;
+    tailr.expect_stdout("one\ntwo\n");
+    drop(writer.stdin.take());
+    writer.wait()?;
+    assert!(tailr.child.wait()?.success());
+    tailr.expect_stdout("one\ntwo\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_fifo() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("fifo");
+    assert!(process::Command::new("mkfifo")
+        .arg(&path)
+        .status()?
+        .success());
+
+    // A FIFO is read to its end, then for whatever later writers write
+    let tailr = Follower::spawn(&["-f", "-s", "0.1", path.to_str().unwrap()])?;
+    OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .write_all(b"one\n")?;
+    tailr.expect_stdout("one\n");
+    OpenOptions::new()
+        .write(true)
+        .open(&path)?
+        .write_all(b"two\n")?;
+    tailr.expect_stdout("one\ntwo\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_descriptor_retry() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("log");
+    let name = path.to_str().unwrap();
+
+    // --retry waits for the file to first appear, as with -F
+    let tailr = Follower::spawn(&["-f", "--retry", "-s", "0.1", name])?;
+    tailr.expect_stderr("tailr: warning: --retry only effective for the initial open\n");
+    tailr.expect_stderr(&format!(
+        "tailr: cannot open '{name}' for reading: No such file or directory"
+    ));
+    fs::write(&path, "one\n")?;
+    tailr.expect_stdout("one\n");
+    append(&path, "two\n")?;
+    tailr.expect_stdout("one\ntwo\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn retry_without_follow() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--retry", ONE])
+        .assert()
+        .success()
+        .stdout("Öne line, four words.\n")
+        .stderr("tailr: warning: --retry ignored; --retry is useful only when following\n");
+    Ok(())
+}
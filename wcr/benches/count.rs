@@ -0,0 +1,85 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::io::{BufRead, Cursor};
+use unicode_width::UnicodeWidthChar;
+
+/// Roughly the size of input each iteration counts.
+const INPUT_SIZE: usize = 8 * 1024 * 1024;
+
+fn input(line: &str) -> Vec<u8> {
+    line.as_bytes()
+        .iter()
+        .copied()
+        .cycle()
+        .take(INPUT_SIZE)
+        .collect()
+}
+
+/// The line-by-line counting that the block-based engine replaced, kept
+/// here to measure the engine against.
+fn count_by_line<R: BufRead>(mut file: R) -> (usize, usize, usize, usize, usize) {
+    let (mut lines, mut words, mut bytes, mut chars, mut max_width) = (0, 0, 0, 0, 0);
+    let mut line = Vec::new();
+
+    while file.read_until(b'\n', &mut line).unwrap() > 0 {
+        if line.ends_with(b"\n") {
+            lines += 1;
+        }
+        bytes += line.len();
+
+        let mut in_word = false;
+        let mut width = 0;
+        for chunk in line.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                chars += 1;
+                if c.is_whitespace() {
+                    in_word = false;
+                } else if !c.is_control() && !in_word {
+                    in_word = true;
+                    words += 1;
+                }
+                match c {
+                    '\t' => width += 8 - width % 8,
+                    _ if c.is_control() => {}
+                    _ => width += c.width().unwrap_or(0),
+                }
+            }
+        }
+        max_width = max_width.max(width);
+        line.clear();
+    }
+
+    (lines, words, bytes, chars, max_width)
+}
+
+fn bench_count(c: &mut Criterion) {
+    let inputs = [
+        (
+            "ascii",
+            input("The quick brown fox jumps over the lazy dog.\tAnd again\n"),
+        ),
+        (
+            "utf8",
+            input("Þá var þat sveinn, er í Atlamálum kvað 漢字\n"),
+        ),
+        ("long_lines", input(&format!("{}\n", "word ".repeat(2000)))),
+    ];
+
+    let mut group = c.benchmark_group("count");
+    group.throughput(Throughput::Bytes(INPUT_SIZE as u64));
+    for (name, bytes) in &inputs {
+        group.bench_with_input(BenchmarkId::new("line_by_line", name), bytes, |b, bytes| {
+            b.iter(|| count_by_line(Cursor::new(black_box(bytes))))
+        });
+        group.bench_with_input(BenchmarkId::new("engine", name), bytes, |b, bytes| {
+            b.iter(|| wcr::count(Cursor::new(black_box(bytes))).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("engine_lines", name), bytes, |b, bytes| {
+            b.iter(|| wcr::count_lines(Cursor::new(black_box(bytes))).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_count);
+criterion_main!(benches);
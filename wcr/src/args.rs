@@ -1,15 +1,154 @@
-use clap::{Arg, Command};
+use crate::groups::GroupBy;
+use crate::records::Format;
+use crate::{describe, FileInfo};
+use clap::{Arg, ArgAction, Command};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
-    pub files: Vec<String>,
+    pub files: Vec<PathBuf>,
     pub lines: bool,
     pub words: bool,
+    pub word_mode: WordMode,
     pub bytes: bool,
     pub chars: bool,
+    pub graphemes: bool,
+    pub max_line_length: bool,
+    pub code: bool,
+    pub stats: bool,
+    pub histogram: bool,
+    pub watch: bool,
+    pub interval: Duration,
+    pub jobs: usize,
+    pub total: Total,
+    pub output_format: Format,
+    pub fixed_width: bool,
+    pub group_by: Option<GroupBy>,
+    pub sort: Option<SortKey>,
+    pub reverse: bool,
+    pub min_lines: Option<usize>,
+    pub max_bytes: Option<usize>,
+    /// Whether some input named with --files0-from or found with -r could
+    /// not be taken, which fails wcr however the rest are counted.
+    pub input_failed: bool,
+}
+
+/// When to print the line of totals, as for GNU wc's `--total`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Total {
+    /// Only when more than one file is counted.
+    Auto,
+    Always,
+    /// Print the totals, without the "total" label, and no per-file counts.
+    Only,
+    Never,
+}
+
+impl Total {
+    pub fn parse(val: &str) -> Result<Total, String> {
+        match val {
+            "auto" => Ok(Total::Auto),
+            "always" => Ok(Total::Always),
+            "only" => Ok(Total::Only),
+            "never" => Ok(Total::Never),
+            _ => Err(format!("unknown value '{}'", val)),
+        }
+    }
+}
+
+/// How `--words` tells where words are.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WordMode {
+    /// Words are separated by white space, as for GNU wc.
+    Whitespace,
+    /// Words are found by their Unicode (UAX #29) boundaries, not counting
+    /// punctuation, so that each ideograph is a word.
+    Unicode,
+}
+
+impl WordMode {
+    pub fn parse(val: &str) -> Result<WordMode, String> {
+        match val {
+            "whitespace" => Ok(WordMode::Whitespace),
+            "unicode" => Ok(WordMode::Unicode),
+            _ => Err(format!("unknown value '{}'", val)),
+        }
+    }
+}
+
+/// The column that `--sort` orders rows by.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortKey {
+    Lines,
+    Words,
+    Bytes,
+    Chars,
+    Graphemes,
+    MaxLineLength,
+    Blank,
+    Comment,
+    Code,
+    Name,
+}
+
+impl SortKey {
+    pub fn parse(val: &str) -> Result<SortKey, String> {
+        match val {
+            "lines" => Ok(SortKey::Lines),
+            "words" => Ok(SortKey::Words),
+            "bytes" => Ok(SortKey::Bytes),
+            "chars" => Ok(SortKey::Chars),
+            "graphemes" => Ok(SortKey::Graphemes),
+            "max-line-length" => Ok(SortKey::MaxLineLength),
+            "blank" => Ok(SortKey::Blank),
+            "comment" => Ok(SortKey::Comment),
+            "code" => Ok(SortKey::Code),
+            "name" => Ok(SortKey::Name),
+            _ => Err(format!("unknown column '{}'", val)),
+        }
+    }
+
+    /// Orders names alphabetically, but counts largest first.
+    pub fn compare(&self, a: (&str, &FileInfo), b: (&str, &FileInfo)) -> std::cmp::Ordering {
+        let count = |info: &FileInfo| match self {
+            SortKey::Lines => info.num_lines,
+            SortKey::Words => info.num_words,
+            SortKey::Bytes => info.num_bytes,
+            SortKey::Chars => info.num_chars,
+            SortKey::Graphemes => info.num_graphemes,
+            SortKey::MaxLineLength => info.max_line_length,
+            SortKey::Blank => info.num_blank,
+            SortKey::Comment => info.num_comment,
+            SortKey::Code => info.num_code,
+            SortKey::Name => 0,
+        };
+        match self {
+            SortKey::Name => a.0.cmp(b.0),
+            _ => count(b.1).cmp(&count(a.1)),
+        }
+    }
+
+    /// Sorts `rows` by the name and counts that `row` gives for each, in the
+    /// order of [`SortKey::compare`], or the other way round with `reverse`.
+    /// Rows that tie keep the order they were in.
+    pub fn sort<T>(&self, rows: &mut [T], reverse: bool, row: impl Fn(&T) -> (&str, &FileInfo)) {
+        rows.sort_by(|a, b| {
+            let ordering = self.compare(row(a), row(b));
+            if reverse {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -22,7 +161,8 @@ pub fn get_args() -> MyResult<Config> {
                 .value_name("FILE")
                 .help("Input file(s)")
                 .num_args(0..)
-                .default_value("-"),
+                .default_value("-")
+                .value_parser(clap::value_parser!(PathBuf)),
         )
         .arg(
             Arg::new("lines")
@@ -34,11 +174,14 @@ pub fn get_args() -> MyResult<Config> {
         )
         .arg(
             Arg::new("words")
-                .value_name("WORDS")
                 .short('w')
                 .long("words")
-                .help("The number of words in each input file is written to the standard output.")
-                .num_args(0)
+                .value_name("MODE")
+                .help("The number of words in each input file is written to the standard output.  MODE is 'whitespace' (the default) for words separated by white space, or 'unicode' for words found by their Unicode boundaries, so that each ideograph is a word and punctuation is not.")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("whitespace")
+                .value_parser(WordMode::parse)
         )
         .arg(
             Arg::new("bytes")
@@ -58,6 +201,178 @@ pub fn get_args() -> MyResult<Config> {
                 .num_args(0)
                 .conflicts_with("bytes")
         )
+        .arg(
+            Arg::new("graphemes")
+                .short('g')
+                .long("graphemes")
+                .help("The number of grapheme clusters in each input file, the characters a reader sees, is written to the standard output.  An accented letter or an emoji made of several code points counts once.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("max_line_length")
+                .value_name("MAX_LINE_LENGTH")
+                .short('L')
+                .long("max-line-length")
+                .help("The display width of the longest line in each input file is written to the standard output.  Tabs advance to the next multiple of 8 and wide characters count as 2 columns.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("code")
+                .long("code")
+                .help("The number of blank, comment and code lines in each input file is written to the standard output, with comments and strings recognized by the file name extension for Rust, C, Python, shell, TOML, YAML and Markdown.  A line with both code and a comment on it is code.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .help("Under the counts of each input file and of the total, the least, greatest, mean, median and 95th percentile line length, as a display width like -L, and number of words per line are written to the standard output.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("histogram")
+                .long("histogram")
+                .help("After the counts, a histogram of the line lengths of all input files is written to the standard output, in buckets of 0, 1, 2-3, 4-7 and so on.  Only with the table --output-format.")
+                .num_args(0),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .help("Count a single input file, or the standard input, as it is read, writing the counts so far and the lines and bytes per second every --interval: as a status line on a terminal, or else as a JSON object on each line.  A regular file is followed as it grows, so wcr runs until interrupted, as with Ctrl-C.")
+                .num_args(0)
+                .conflicts_with_all([
+                    "code",
+                    "graphemes",
+                    "stats",
+                    "histogram",
+                    "jobs",
+                    "total",
+                    "output_format",
+                    "fixed_width",
+                    "files0_from",
+                    "recursive",
+                    "group_by",
+                    "sort",
+                    "min_lines",
+                    "max_bytes",
+                ]),
+        )
+        .arg(
+            Arg::new("interval")
+                .value_name("SECONDS")
+                .long("interval")
+                .help("With --watch, how often to write the counts, in seconds.")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(parse_interval)
+                .requires("watch"),
+        )
+        .arg(
+            Arg::new("jobs")
+                .value_name("N")
+                .short('j')
+                .long("jobs")
+                .help("Count up to N files at once, still printing them in order.  A single large file is split into up to N chunks counted at once.")
+                .num_args(1)
+                .default_value("1")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("total")
+                .value_name("WHEN")
+                .long("total")
+                .help("When to print a line with total counts; WHEN is 'auto' (the default, when more than one file is counted), 'always', 'only' or 'never'.")
+                .num_args(1)
+                .require_equals(true)
+                .default_value("auto")
+                .value_parser(Total::parse),
+        )
+        .arg(
+            Arg::new("output_format")
+                .value_name("FORMAT")
+                .long("output-format")
+                .help("Print the counts as 'table' (the default), or as records for each file and the total in 'json', 'jsonl', 'csv' or 'tsv'.  Every record has all the count fields, with those that were not asked for null, or empty in CSV and TSV.")
+                .num_args(1)
+                .default_value("table")
+                .value_parser(Format::parse),
+        )
+        .arg(
+            Arg::new("fixed_width")
+                .long("fixed-width")
+                .help("Print every count 8 wide, rather than as wide as the largest count needs.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("files0_from")
+                .value_name("F")
+                .long("files0-from")
+                .help("Read the names of the input files from F, each ended by a NUL character, rather than from the command line; F is '-' for the standard input.")
+                .num_args(1)
+                .conflicts_with("files"),
+        )
+        .arg(
+            Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .help("Count the files in each directory, and in all the directories under it.")
+                .num_args(0)
+        )
+        .arg(
+            Arg::new("include")
+                .value_name("GLOB")
+                .long("include")
+                .help("With -r, count only the files whose name or path matches GLOB.  May be given more than once.")
+                .action(ArgAction::Append)
+                .value_parser(parse_glob)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .value_name("GLOB")
+                .long("exclude")
+                .help("With -r, skip the files and directories whose name or path matches GLOB.  May be given more than once.")
+                .action(ArgAction::Append)
+                .value_parser(parse_glob)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::new("group_by")
+                .value_name("GROUP")
+                .long("group-by")
+                .help("Print subtotals for groups of files rather than counts for each file; GROUP is 'ext' for the file name extension, 'dir' for the directory, or 'depth:N' for the first N components of the directory.")
+                .num_args(1)
+                .value_parser(GroupBy::parse),
+        )
+        .arg(
+            Arg::new("sort")
+                .value_name("COLUMN")
+                .long("sort")
+                .help("Order the files, or with --group-by the groups, by COLUMN, one of 'lines', 'words', 'bytes', 'chars', 'graphemes', 'max-line-length', 'blank', 'comment' or 'code' largest first, or 'name'.  The files are printed once all of them are counted.")
+                .num_args(1)
+                .value_parser(SortKey::parse),
+        )
+        .arg(
+            Arg::new("reverse")
+                .long("reverse")
+                .help("With --sort, order by COLUMN the other way round: smallest first, or names from Z to A.")
+                .num_args(0)
+                .requires("sort"),
+        )
+        .arg(
+            Arg::new("min_lines")
+                .value_name("N")
+                .long("min-lines")
+                .help("Leave out the files with fewer than N lines, from the total and groups too.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("max_bytes")
+                .value_name("N")
+                .long("max-bytes")
+                .help("Leave out the files with more than N bytes, from the total and groups too.")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64)),
+        )
         .get_matches();
 
     // let files = matches
@@ -67,21 +382,66 @@ pub fn get_args() -> MyResult<Config> {
     //     .collect();
 
     // if "files" is missing, it returns an empty Vec<String>.
-    let files: Vec<String> = matches
-        .get_many::<String>("files")
-        .map(|v| v.map(ToOwned::to_owned).collect())
-        .unwrap_or_default();
+    let mut input_failed = false;
+    let mut files: Vec<PathBuf> = match matches.get_one::<String>("files0_from") {
+        Some(source) => read_files0(source, &mut input_failed)?,
+        None => matches
+            .get_many::<PathBuf>("files")
+            .map(|v| v.map(ToOwned::to_owned).collect())
+            .unwrap_or_default(),
+    };
+
+    if matches.get_flag("recursive") {
+        let include = glob_set(matches.get_many::<Glob>("include"))?;
+        let exclude = glob_set(matches.get_many::<Glob>("exclude"))?;
+        files = walk(
+            &files,
+            include.as_ref(),
+            exclude.as_ref(),
+            &mut input_failed,
+        );
+    }
 
     let lines = matches.get_flag("lines");
-    let words = matches.get_flag("words");
+    let word_mode = matches.get_one::<WordMode>("words").copied();
+    let words = word_mode.is_some();
     let bytes = matches.get_flag("bytes");
     let chars = matches.get_flag("chars");
+    let graphemes = matches.get_flag("graphemes");
+    let max_line_length = matches.get_flag("max_line_length");
 
-    let (lines, words, bytes) = if !(lines || words || bytes || chars) {
-        (true, true, true)
-    } else {
-        (lines, words, bytes)
-    };
+    let (lines, words, bytes) =
+        if !(lines || words || bytes || chars || graphemes || max_line_length) {
+            (true, true, true)
+        } else {
+            (lines, words, bytes)
+        };
+
+    let code = matches.get_flag("code");
+    let sort = matches.get_one::<SortKey>("sort").copied();
+    let watch = matches.get_flag("watch");
+    let output_format = matches.get_one::<Format>("output_format").copied().unwrap();
+    let histogram = matches.get_flag("histogram");
+    if histogram && output_format != Format::Table {
+        return Err(From::from(
+            "--histogram prints only with --output-format=table",
+        ));
+    }
+    if watch && word_mode == Some(WordMode::Unicode) {
+        return Err(From::from("--watch counts words only by white space"));
+    }
+
+    match sort {
+        Some(SortKey::Graphemes) if !graphemes => {
+            return Err(From::from("--sort=graphemes needs -g/--graphemes"));
+        }
+        Some(SortKey::Blank | SortKey::Comment | SortKey::Code) if !code => {
+            return Err(From::from(
+                "--sort by blank, comment or code lines needs --code",
+            ));
+        }
+        _ => {}
+    }
 
     // let mut lines = matches.get_flag("lines");
     // let mut words = matches.get_flag("words");
@@ -98,7 +458,148 @@ pub fn get_args() -> MyResult<Config> {
         files,
         lines,
         words,
+        word_mode: word_mode.unwrap_or(WordMode::Whitespace),
         bytes,
         chars,
+        graphemes,
+        max_line_length,
+        code,
+        stats: matches.get_flag("stats"),
+        histogram,
+        watch,
+        interval: matches.get_one::<Duration>("interval").copied().unwrap(),
+        jobs: matches.get_one::<u64>("jobs").copied().unwrap() as usize,
+        total: matches.get_one::<Total>("total").copied().unwrap(),
+        output_format,
+        fixed_width: matches.get_flag("fixed_width"),
+        group_by: matches.get_one::<GroupBy>("group_by").copied(),
+        sort,
+        reverse: matches.get_flag("reverse"),
+        min_lines: matches.get_one::<u64>("min_lines").map(|&n| n as usize),
+        max_bytes: matches.get_one::<u64>("max_bytes").map(|&n| n as usize),
+        input_failed,
     })
 }
+
+fn parse_interval(val: &str) -> Result<Duration, String> {
+    match val.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("invalid interval '{}'", val)),
+    }
+}
+
+fn parse_glob(pattern: &str) -> Result<Glob, String> {
+    Glob::new(pattern).map_err(|e| e.to_string())
+}
+
+fn glob_set<'a>(globs: Option<impl Iterator<Item = &'a Glob>>) -> MyResult<Option<GlobSet>> {
+    let Some(globs) = globs else {
+        return Ok(None);
+    };
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(glob.clone());
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Reads file names separated by NUL characters from `source`, or from the
+/// standard input when it is "-", so that names with spaces or newlines in
+/// them come through whole, as do names that are not UTF-8. Names that
+/// cannot be counted are left out and set `failed`.
+fn read_files0(source: &str, failed: &mut bool) -> MyResult<Vec<PathBuf>> {
+    let mut names = Vec::new();
+    match source {
+        "-" => io::stdin().read_to_end(&mut names),
+        _ => fs::File::open(source).and_then(|mut file| file.read_to_end(&mut names)),
+    }
+    .map_err(|e| format!("cannot open '{}' for reading: {}", source, describe(&e)))?;
+
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    if names.last() == Some(&0) {
+        names.pop();
+    }
+
+    let mut found = Vec::new();
+    for name in names.split(|&byte| byte == 0) {
+        if name.is_empty() {
+            eprintln!("wcr: invalid zero-length file name");
+            *failed = true;
+        } else if source == "-" && name == b"-" {
+            // The standard input is already taken by the names themselves
+            eprintln!("wcr: no file name of '-' is allowed when reading names from stdin");
+            *failed = true;
+        } else {
+            found.push(path_from_bytes(name));
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Replaces each directory in `files` with the regular files under it, in
+/// order of their paths. Files given by name are kept whatever their name,
+/// but the files found under a directory must match `include`, if given, and
+/// neither they nor the directories they are in may match `exclude`. Those
+/// that cannot be read are left out and set `failed`.
+fn walk(
+    files: &[PathBuf],
+    include: Option<&GlobSet>,
+    exclude: Option<&GlobSet>,
+    failed: &mut bool,
+) -> Vec<PathBuf> {
+    let matches = |set: &GlobSet, path: &Path| {
+        set.is_match(path) || path.file_name().is_some_and(|name| set.is_match(name))
+    };
+
+    let mut found = Vec::new();
+    for filename in files {
+        if filename.as_os_str() == "-" || !filename.is_dir() {
+            found.push(filename.clone());
+            continue;
+        }
+
+        let entries = WalkDir::new(filename)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0 || !exclude.is_some_and(|set| matches(set, entry.path()))
+            });
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    let path = error.path().unwrap_or(filename);
+                    match error.io_error() {
+                        Some(io_error) => {
+                            eprintln!("wcr: {}: {}", path.display(), describe(io_error))
+                        }
+                        None => eprintln!("wcr: {}: {}", path.display(), error),
+                    }
+                    *failed = true;
+                    continue;
+                }
+            };
+            let included = include.is_none_or(|set| matches(set, entry.path()));
+            if !entry.file_type().is_file() || !included {
+                continue;
+            }
+            found.push(entry.into_path());
+        }
+    }
+    found
+}
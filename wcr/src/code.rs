@@ -0,0 +1,387 @@
+use crate::engine::LineCounter;
+use crate::FileInfo;
+use std::path::Path;
+
+/// The comment and string syntax of a language, as far as telling code from
+/// comments needs.
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Whether block comments nest, as in Rust.
+    nested: bool,
+    /// String delimiters, longer ones before any they start with.
+    quotes: &'static [&'static str],
+    /// The delimiters of strings that may go on past the end of a line;
+    /// any other string ends with its line.
+    multiline: &'static [&'static str],
+    /// The delimiters of strings in which `\` escapes nothing.
+    raw: &'static [&'static str],
+    /// Whether a quote must start a value to start a string, so that an
+    /// apostrophe inside a plain YAML value is just an apostrophe.
+    quote_starts_value: bool,
+    /// Whether a line comment must start a word, as `#` must in shell and
+    /// YAML.
+    comment_after_space: bool,
+    /// Whether `'` starts a character literal, but is otherwise a lifetime,
+    /// as in Rust.
+    char_literals: bool,
+}
+
+const RUST: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested: true,
+    quotes: &["\""],
+    multiline: &["\""],
+    raw: &[],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: true,
+};
+
+const C: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested: false,
+    quotes: &["\"", "'"],
+    multiline: &[],
+    raw: &[],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: false,
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested: false,
+    quotes: &["\"\"\"", "'''", "\"", "'"],
+    multiline: &["\"\"\"", "'''"],
+    raw: &[],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: false,
+};
+
+const SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested: false,
+    quotes: &["\"", "'"],
+    multiline: &["\"", "'"],
+    raw: &["'"],
+    quote_starts_value: false,
+    comment_after_space: true,
+    char_literals: false,
+};
+
+const TOML: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested: false,
+    quotes: &["\"\"\"", "'''", "\"", "'"],
+    multiline: &["\"\"\"", "'''"],
+    raw: &["'''", "'"],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: false,
+};
+
+const YAML: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested: false,
+    quotes: &["\"", "'"],
+    multiline: &[],
+    raw: &["'"],
+    quote_starts_value: true,
+    comment_after_space: true,
+    char_literals: false,
+};
+
+const MARKDOWN: Syntax = Syntax {
+    line_comments: &[],
+    block_comment: Some(("<!--", "-->")),
+    nested: false,
+    quotes: &[],
+    multiline: &[],
+    raw: &[],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: false,
+};
+
+/// Files in other languages have only blank lines and code.
+const PLAIN: Syntax = Syntax {
+    line_comments: &[],
+    block_comment: None,
+    nested: false,
+    quotes: &[],
+    multiline: &[],
+    raw: &[],
+    quote_starts_value: false,
+    comment_after_space: false,
+    char_literals: false,
+};
+
+/// The syntax of the language that `filename` is in, by its extension.
+fn syntax(path: &Path) -> &'static Syntax {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("");
+    match extension {
+        "rs" => &RUST,
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" => &C,
+        "py" => &PYTHON,
+        "sh" | "bash" | "zsh" => &SHELL,
+        "toml" => &TOML,
+        "yml" | "yaml" => &YAML,
+        "md" | "markdown" => &MARKDOWN,
+        _ => &PLAIN,
+    }
+}
+
+/// Sorts the lines of a file into blank lines, comments and code. A line is
+/// code if it has anything but a comment on it, including being inside a
+/// string, a comment if it has nothing but a comment, and blank if it has
+/// nothing but white space.
+pub struct Classifier {
+    syntax: &'static Syntax,
+    /// How deep in block comments the current line starts.
+    depth: usize,
+    /// The delimiter that ends the string the current line starts in.
+    string: Option<&'static str>,
+    counts: FileInfo,
+}
+
+impl Classifier {
+    pub fn new(path: &Path) -> Classifier {
+        Classifier {
+            syntax: syntax(path),
+            depth: 0,
+            string: None,
+            counts: FileInfo::default(),
+        }
+    }
+}
+
+impl LineCounter for Classifier {
+    fn line(&mut self, line: &[u8]) {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let syntax = self.syntax;
+        let (mut code, mut comment) = (false, false);
+        let mut i = 0;
+
+        while i < line.len() {
+            let rest = &line[i..];
+            if let Some(quote) = self.string {
+                code = true;
+                if rest[0] == b'\\' && !syntax.raw.contains(&quote) {
+                    i += 2;
+                } else if rest.starts_with(quote.as_bytes()) {
+                    self.string = None;
+                    i += quote.len();
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+
+            if self.depth > 0 {
+                comment = true;
+                let (open, close) = syntax.block_comment.unwrap();
+                if syntax.nested && rest.starts_with(open.as_bytes()) {
+                    self.depth += 1;
+                    i += open.len();
+                } else if rest.starts_with(close.as_bytes()) {
+                    self.depth -= 1;
+                    i += close.len();
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+
+            if rest[0].is_ascii_whitespace() {
+                i += 1;
+                continue;
+            }
+
+            let starts_word = i == 0 || line[i - 1].is_ascii_whitespace();
+            let line_comment = syntax
+                .line_comments
+                .iter()
+                .any(|start| rest.starts_with(start.as_bytes()));
+            if line_comment && (starts_word || !syntax.comment_after_space) {
+                comment = true;
+                break;
+            }
+
+            if let Some((open, _)) = syntax.block_comment {
+                if rest.starts_with(open.as_bytes()) {
+                    comment = true;
+                    self.depth = 1;
+                    i += open.len();
+                    continue;
+                }
+            }
+
+            code = true;
+            if syntax.char_literals && rest[0] == b'\'' {
+                i += char_literal_len(rest).unwrap_or(1);
+            } else if let Some(quote) = syntax
+                .quotes
+                .iter()
+                .find(|q| rest.starts_with(q.as_bytes()))
+                .filter(|_| !syntax.quote_starts_value || starts_value(line, i))
+            {
+                self.string = Some(quote);
+                i += quote.len();
+            } else {
+                i += 1;
+            }
+        }
+
+        if self
+            .string
+            .is_some_and(|quote| !syntax.multiline.contains(&quote))
+        {
+            self.string = None;
+        }
+
+        if code {
+            self.counts.num_code += 1;
+        } else if comment {
+            self.counts.num_comment += 1;
+        } else {
+            self.counts.num_blank += 1;
+        }
+    }
+
+    fn finish(self) -> FileInfo {
+        self.counts
+    }
+}
+
+/// Whether what is at `i` in `line` starts a value, being at the start of
+/// the line or after white space or the punctuation of a YAML flow
+/// collection.
+fn starts_value(line: &[u8], i: usize) -> bool {
+    i == 0 || matches!(line[i - 1], b' ' | b'\t' | b'[' | b'{' | b',')
+}
+
+/// The length of the character literal that `rest` starts with, or `None`
+/// if its `'` starts a lifetime instead.
+fn char_literal_len(rest: &[u8]) -> Option<usize> {
+    if rest.get(1) == Some(&b'\\') {
+        // Past the escaped character, which may itself be a quote
+        return rest
+            .get(3..)?
+            .iter()
+            .position(|&byte| byte == b'\'')
+            .map(|end| end + 4);
+    }
+    let len = match *rest.get(1)? {
+        byte if byte < 0x80 => 1,
+        byte if byte >= 0xf0 => 4,
+        byte if byte >= 0xe0 => 3,
+        _ => 2,
+    };
+    (rest.get(len + 1) == Some(&b'\'')).then_some(len + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Classifier;
+    use crate::engine::Lines;
+    use crate::Metric;
+    use std::path::Path;
+
+    /// The (blank, comment, code) counts of `text` as the file `filename`.
+    fn classify(filename: &str, text: &str) -> (usize, usize, usize) {
+        let mut classifier = Lines::new(Classifier::new(Path::new(filename)));
+        classifier.update(text.as_bytes());
+        let counts = classifier.into_counts();
+        (counts.num_blank, counts.num_comment, counts.num_code)
+    }
+
+    #[test]
+    fn test_rust() {
+        let text = r#"// A comment
+/// Docs
+fn main() { // trailing
+    let s = "// not a comment";
+
+    /* block
+       /* nested */
+       still comment */
+    let c = '"'; let r = 'x';
+    let t = "multi
+// line string";
+}
+fn f<'a>(x: &'a str) {} /* lifetime, then a comment */
+/**/
+"#;
+        assert_eq!(classify("main.rs", text), (1, 6, 7));
+    }
+
+    #[test]
+    fn test_c() {
+        let text = "#include <stdio.h>\n/* a\n * b */\nint x = '\\''; // q\nchar *s = \"/*\";\n\n";
+        assert_eq!(classify("x.c", text), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_python() {
+        let text = "# comment\nx = 1  # trailing\ns = \"\"\"\n# in a docstring\n\"\"\"\n\n";
+        assert_eq!(classify("x.py", text), (1, 1, 4));
+    }
+
+    #[test]
+    fn test_shell_and_yaml() {
+        let text = "#!/bin/sh\necho ${#x} $# # comment\n  # indented\n";
+        assert_eq!(classify("x.sh", text), (0, 2, 1));
+        let text = "key: value # comment\nurl: http://x/#anchor\n# comment\n";
+        assert_eq!(classify("x.yaml", text), (0, 1, 2));
+    }
+
+    #[test]
+    fn test_strings_end_with_line() {
+        // An apostrophe inside a plain YAML value starts no string
+        let text = "title: it's here\n# comment\nname: 'quoted # not a comment'\n# comment\n";
+        assert_eq!(classify("x.yaml", text), (0, 2, 2));
+        // A string left open ends with its line, unless it may go on
+        assert_eq!(classify("x.py", "x = 'abc\n# comment\n"), (0, 1, 1));
+        assert_eq!(
+            classify("x.py", "x = \"\"\"abc\n# docs\n\"\"\"\n"),
+            (0, 0, 3)
+        );
+        // A backslash escapes nothing in single quotes in shell
+        assert_eq!(classify("x.sh", "echo 'a\\'\n# comment\n"), (0, 1, 1));
+    }
+
+    #[test]
+    fn test_toml_and_markdown() {
+        let text = "[package]\n# comment\nname = \"#wcr\"\n\n";
+        assert_eq!(classify("Cargo.toml", text), (1, 1, 2));
+        let text = "# Title\n\n<!-- hidden\n-->\nText <!-- c -->\n";
+        assert_eq!(classify("README.md", text), (1, 2, 2));
+    }
+
+    #[test]
+    fn test_plain_and_blocks() {
+        assert_eq!(classify("notes.txt", "a\n\n  \n# b"), (2, 0, 2));
+
+        // Lines and comments split between blocks are classified whole
+        let mut classifier = Lines::new(Classifier::new(Path::new("x.rs")));
+        for byte in "fn f() {} /* a\nb */ // c\n\nx\n".as_bytes() {
+            classifier.update(std::slice::from_ref(byte));
+        }
+        let counts = classifier.into_counts();
+        assert_eq!(
+            (counts.num_blank, counts.num_comment, counts.num_code),
+            (1, 1, 2)
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::engine::{Scanner, BLOCK_SIZE};
+use std::io::{self, ErrorKind, Read};
+
+/// The counts of an input as GNU wc takes them: its lines, that is its
+/// newlines, its words separated by white space, its bytes and characters,
+/// and the display width of its longest line.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Counts {
+    pub lines: usize,
+    pub words: usize,
+    pub bytes: usize,
+    pub chars: usize,
+    pub max_line_length: usize,
+}
+
+/// A count of your own, taken by a [`Counter`] in the same pass as its
+/// counts. It is given the whole input in order, but in chunks that may end
+/// anywhere, even in the middle of a line or a character.
+///
+/// ```
+/// use wcr::{Counter, Metric};
+///
+/// #[derive(Default)]
+/// struct Tabs(usize);
+///
+/// impl Metric for Tabs {
+///     fn update(&mut self, chunk: &[u8]) {
+///         self.0 += chunk.iter().filter(|&&b| b == b'\t').count();
+///     }
+/// }
+///
+/// let mut tabs = Tabs::default();
+/// let mut counter = Counter::new().with_metric(&mut tabs);
+/// counter.update(b"a\tb\n\tc");
+/// let counts = counter.finish();
+/// assert_eq!((counts.lines, counts.words, tabs.0), (1, 3, 2));
+/// ```
+pub trait Metric {
+    fn update(&mut self, chunk: &[u8]);
+
+    /// Called once the input has ended, for a count that holds on to the
+    /// end of a chunk until it sees what follows.
+    fn finish(&mut self) {}
+}
+
+/// Counts an input given a chunk at a time, for input that does not come
+/// from a reader, or to have the counts so far while it is still coming.
+pub struct Counter<'a> {
+    scanner: Scanner,
+    metrics: Vec<&'a mut dyn Metric>,
+}
+
+impl<'a> Counter<'a> {
+    pub fn new() -> Counter<'a> {
+        Counter::decoding(true)
+    }
+
+    /// A counter of only lines and bytes, which is much faster as the input
+    /// need not be decoded. The other counts stay 0.
+    pub fn lines_only() -> Counter<'a> {
+        Counter::decoding(false)
+    }
+
+    pub(crate) fn decoding(decode: bool) -> Counter<'a> {
+        Counter {
+            scanner: Scanner::new(decode),
+            metrics: Vec::new(),
+        }
+    }
+
+    /// Gives `metric` every chunk too. It is borrowed until the counter
+    /// finishes, after which its count can be read.
+    pub fn with_metric(mut self, metric: &'a mut dyn Metric) -> Counter<'a> {
+        self.metrics.push(metric);
+        self
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.scanner.feed(chunk);
+        for metric in &mut self.metrics {
+            metric.update(chunk);
+        }
+    }
+
+    /// Counts what is left of `reader`, a block at a time.
+    pub fn read_from<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut block = vec![0; BLOCK_SIZE];
+        loop {
+            match reader.read(&mut block) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.update(&block[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The counts so far, as if the input ended here.
+    pub fn counts(&self) -> Counts {
+        self.scanner.counts()
+    }
+
+    pub fn finish(self) -> Counts {
+        for metric in self.metrics {
+            metric.finish();
+        }
+        self.scanner.counts()
+    }
+}
+
+impl Default for Counter<'_> {
+    fn default() -> Self {
+        Counter::new()
+    }
+}
+
+/// Counts the lines, words, bytes and characters of `file` and the display
+/// width of its longest line.
+pub fn count<R: Read>(file: R) -> io::Result<Counts> {
+    let mut counter = Counter::new();
+    counter.read_from(file)?;
+    Ok(counter.finish())
+}
+
+/// Counts only the lines and bytes of `file`, which is much faster than
+/// [`count`] as the input need not be decoded.
+pub fn count_lines<R: Read>(file: R) -> io::Result<Counts> {
+    let mut counter = Counter::lines_only();
+    counter.read_from(file)?;
+    Ok(counter.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count, count_lines, Counter, Counts, Metric};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_count_lines() {
+        let counts = count_lines(Cursor::new("one two\nthree 漢字\nfour")).unwrap();
+        let expected = Counts {
+            lines: 2,
+            bytes: 25,
+            ..Default::default()
+        };
+        assert_eq!(counts, expected);
+    }
+
+    #[test]
+    fn test_counts_so_far() {
+        // The counts so far are those of all that has been fed, even when it
+        // ends in the middle of a line or a character
+        let bytes = "Þá var þat\nsveinn, er í".as_bytes();
+        let mut counter = Counter::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            counter.update(std::slice::from_ref(byte));
+            let expected = count(Cursor::new(&bytes[..=i])).unwrap();
+            assert_eq!(counter.counts(), expected, "after {} bytes", i + 1);
+        }
+    }
+
+    /// The longest run of bytes that are not white space.
+    #[derive(Default)]
+    struct LongestWord {
+        run: usize,
+        longest: usize,
+    }
+
+    impl Metric for LongestWord {
+        fn update(&mut self, chunk: &[u8]) {
+            for byte in chunk {
+                self.run = if byte.is_ascii_whitespace() {
+                    0
+                } else {
+                    self.run + 1
+                };
+                self.longest = self.longest.max(self.run);
+            }
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let (mut first, mut second) = (LongestWord::default(), LongestWord::default());
+        let mut counter = Counter::new()
+            .with_metric(&mut first)
+            .with_metric(&mut second);
+        counter.read_from(Cursor::new("a bcd\nef")).unwrap();
+        counter.update(b"gh ij\n");
+        assert_eq!(counter.finish().words, 4);
+        assert_eq!((first.longest, second.longest), (4, 4));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::{Counter, Counts, FileInfo, Metric, MyResult};
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+use unicode_width::UnicodeWidthChar;
+
+/// How much of the input is read and scanned at a time.
+pub(crate) const BLOCK_SIZE: usize = 128 * 1024;
+
+/// The least a chunk of a file counted in parallel is worth a thread for.
+pub(crate) const MIN_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Counts the regular file at `path`, `size` bytes long, as `chunks` chunks
+/// on as many threads. Every chunk but the first starts just after a
+/// newline, so that no line, word or character is split between two chunks
+/// and their counts simply add up.
+pub(crate) fn count_chunked(
+    path: &Path,
+    size: u64,
+    chunks: u64,
+    decode: bool,
+) -> MyResult<FileInfo> {
+    let mut file = File::open(path)?;
+    let mut bounds = vec![0];
+    for i in 1..chunks {
+        let start = line_start_after(&mut file, size * i / chunks)?;
+        if start > *bounds.last().unwrap() && start < size {
+            bounds.push(start);
+        }
+    }
+    bounds.push(size);
+
+    let results: Vec<io::Result<Counts>> = thread::scope(|scope| {
+        let handles: Vec<_> = bounds
+            .windows(2)
+            .map(|bound| {
+                let (start, end) = (bound[0], bound[1]);
+                scope.spawn(move || {
+                    let mut file = File::open(path)?;
+                    file.seek(SeekFrom::Start(start))?;
+                    let mut counter = Counter::decoding(decode);
+                    counter.read_from(file.take(end - start))?;
+                    Ok(counter.finish())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut info = FileInfo::default();
+    for result in results {
+        info.merge(&FileInfo::from(result?));
+    }
+    Ok(info)
+}
+
+/// Finds where the first line starting after `pos` in `file` starts, or the
+/// end of the file if there is none.
+fn line_start_after(file: &mut File, mut pos: u64) -> io::Result<u64> {
+    file.seek(SeekFrom::Start(pos))?;
+    let mut block = vec![0; BLOCK_SIZE];
+    loop {
+        let n = match file.read(&mut block) {
+            Ok(0) => return Ok(pos),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(i) = memchr::memchr(b'\n', &block[..n]) {
+            return Ok(pos + i as u64 + 1);
+        }
+        pos += n as u64;
+    }
+}
+
+/// A count taken a line at a time, for counts that need to see whole lines.
+pub(crate) trait LineCounter {
+    /// Counts `line`, which ends with its newline unless it is the last.
+    fn line(&mut self, line: &[u8]);
+
+    fn finish(self) -> FileInfo;
+}
+
+/// Splits blocks read one after another into lines for a [`LineCounter`],
+/// carrying a line that a block does not finish over to the next block.
+pub(crate) struct Lines<C> {
+    counter: C,
+    line: Vec<u8>,
+}
+
+impl<C: LineCounter> Lines<C> {
+    pub fn new(counter: C) -> Lines<C> {
+        Lines {
+            counter,
+            line: Vec::new(),
+        }
+    }
+
+    /// The counts of every line fed, the last one included.
+    pub fn into_counts(mut self) -> FileInfo {
+        self.finish();
+        self.counter.finish()
+    }
+}
+
+impl<C: LineCounter> Metric for Lines<C> {
+    fn update(&mut self, mut chunk: &[u8]) {
+        while let Some(end) = memchr::memchr(b'\n', chunk) {
+            if self.line.is_empty() {
+                self.counter.line(&chunk[..=end]);
+            } else {
+                self.line.extend_from_slice(&chunk[..=end]);
+                self.counter.line(&self.line);
+                self.line.clear();
+            }
+            chunk = &chunk[end + 1..];
+        }
+        self.line.extend_from_slice(chunk);
+    }
+
+    /// Counts the last line, if it has no newline.
+    fn finish(&mut self) {
+        if !self.line.is_empty() {
+            self.counter.line(&self.line);
+            self.line.clear();
+        }
+    }
+}
+
+/// Counts the words of a single line and its display width, as `-w` and
+/// `-L` would.
+pub(crate) fn count_line(line: &[u8]) -> Counts {
+    let mut scanner = Scanner::new(true);
+    scanner.feed(line);
+    scanner.counts()
+}
+
+/// A state machine that counts a stream fed to it block by block, carrying
+/// the current word, the current line's width and any UTF-8 character split
+/// between blocks over to the next block.
+pub(crate) struct Scanner {
+    counts: Counts,
+    decode: bool,
+    in_word: bool,
+    line_width: usize,
+    partial: Vec<u8>,
+}
+
+impl Scanner {
+    pub fn new(decode: bool) -> Scanner {
+        Scanner {
+            counts: Counts::default(),
+            decode,
+            in_word: false,
+            line_width: 0,
+            partial: Vec::with_capacity(4),
+        }
+    }
+
+    pub fn feed(&mut self, mut block: &[u8]) {
+        self.counts.bytes += block.len();
+        self.counts.lines += memchr::memchr_iter(b'\n', block).count();
+        if !self.decode {
+            return;
+        }
+
+        if !self.partial.is_empty() {
+            block = self.complete_partial(block);
+        }
+
+        let mut offset = 0;
+        for chunk in block.utf8_chunks() {
+            let valid = chunk.valid();
+            self.text(valid);
+
+            // Invalid bytes are not characters and are skipped, unless they
+            // may be the start of a character that the next block finishes.
+            let invalid = chunk.invalid();
+            offset += valid.len() + invalid.len();
+            if offset == block.len() && is_incomplete(invalid) {
+                self.partial.extend_from_slice(invalid);
+            }
+        }
+    }
+
+    /// Finishes the character begun at the end of the previous block with
+    /// the first bytes of `block`, returning the rest of `block`.
+    fn complete_partial<'a>(&mut self, block: &'a [u8]) -> &'a [u8] {
+        let mut partial = std::mem::take(&mut self.partial);
+        let carried = partial.len();
+        let take = (4 - carried).min(block.len());
+        partial.extend_from_slice(&block[..take]);
+
+        let used = match std::str::from_utf8(&partial) {
+            Ok(s) => s.chars().next().map_or(0, char::len_utf8),
+            Err(e) if e.valid_up_to() > 0 => e.valid_up_to(),
+            Err(e) => match e.error_len() {
+                Some(len) => len,
+                // Still not enough bytes to tell, so wait for another block
+                None => {
+                    self.partial = partial;
+                    return &block[take..];
+                }
+            },
+        };
+
+        if let Ok(s) = std::str::from_utf8(&partial[..used]) {
+            self.text(s);
+        }
+        partial.clear();
+        self.partial = partial;
+        &block[used.max(carried) - carried..]
+    }
+
+    /// Counts valid text, taking runs of ASCII a block at a time.
+    fn text(&mut self, mut text: &str) {
+        while !text.is_empty() {
+            let ascii = text
+                .bytes()
+                .position(|b| !b.is_ascii())
+                .unwrap_or(text.len());
+            self.ascii(&text.as_bytes()[..ascii]);
+
+            let mut chars = text[ascii..].chars();
+            if let Some(c) = chars.next() {
+                self.char(c);
+            }
+            text = chars.as_str();
+        }
+    }
+
+    fn ascii(&mut self, bytes: &[u8]) {
+        // Kept in locals rather than in self so that they stay in registers
+        let mut in_word = self.in_word;
+        let mut width = self.line_width;
+        let mut max_width = self.counts.max_line_length;
+        let mut words = 0;
+
+        for &b in bytes {
+            match b {
+                b'!'..=b'~' => {
+                    words += usize::from(!in_word);
+                    in_word = true;
+                    width += 1;
+                }
+                b' ' => {
+                    in_word = false;
+                    width += 1;
+                }
+                b'\n' | b'\r' | b'\x0c' => {
+                    in_word = false;
+                    max_width = max_width.max(width);
+                    width = 0;
+                }
+                b'\t' => {
+                    in_word = false;
+                    width += 8 - width % 8;
+                }
+                b'\x0b' => in_word = false,
+                _ => {}
+            }
+        }
+
+        self.in_word = in_word;
+        self.line_width = width;
+        self.counts.max_line_length = max_width;
+        self.counts.words += words;
+        self.counts.chars += bytes.len();
+    }
+
+    fn char(&mut self, c: char) {
+        self.counts.chars += 1;
+        if is_space(c) {
+            self.in_word = false;
+        } else if !c.is_control() {
+            self.start_word();
+        }
+        if !c.is_control() {
+            self.line_width += c.width().unwrap_or(0);
+        }
+    }
+
+    fn start_word(&mut self) {
+        if !self.in_word {
+            self.in_word = true;
+            self.counts.words += 1;
+        }
+    }
+
+    /// The counts so far, as if the input ended here.
+    pub fn counts(&self) -> Counts {
+        Counts {
+            max_line_length: self.counts.max_line_length.max(self.line_width),
+            ..self.counts
+        }
+    }
+}
+
+/// Whether `bytes` are the start of a UTF-8 character that is cut short,
+/// rather than bytes that can never be part of one.
+fn is_incomplete(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && matches!(std::str::from_utf8(bytes), Err(e) if e.error_len().is_none())
+}
+
+/// Unicode whitespace, plus the non-breaking spaces GNU wc also treats as
+/// separating words.
+fn is_space(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '\u{2007}' | '\u{2060}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_chunked, Scanner};
+    use crate::{Counts, FileInfo};
+    use std::path::Path;
+
+    fn scan(bytes: &[u8]) -> Counts {
+        let mut scanner = Scanner::new(true);
+        scanner.feed(bytes);
+        scanner.counts()
+    }
+
+    fn words_chars(bytes: &[u8]) -> (usize, usize) {
+        let counts = scan(bytes);
+        (counts.words, counts.chars)
+    }
+
+    fn width(bytes: &[u8]) -> usize {
+        scan(bytes).max_line_length
+    }
+
+    #[test]
+    fn test_words_chars() {
+        assert_eq!(words_chars(b""), (0, 0));
+        assert_eq!(words_chars(b"  one two\tthree\n"), (3, 16));
+        assert_eq!(words_chars("Öne line".as_bytes()), (2, 8));
+        // Non-breaking and ideographic spaces separate words
+        assert_eq!(words_chars("a\u{a0}b\u{3000}c".as_bytes()), (3, 5));
+        // Control characters do not start words, nor break them
+        assert_eq!(words_chars(b"\x01 a\x02b"), (1, 5));
+        // A character cut short at the end is not counted
+        assert_eq!(words_chars(b"a\xc3"), (1, 1));
+    }
+
+    #[test]
+    fn test_line_width() {
+        assert_eq!(width(b""), 0);
+        assert_eq!(width(b"abc\n"), 3);
+        // Tabs advance to the next multiple of 8
+        assert_eq!(width(b"ab\tc\n"), 9);
+        assert_eq!(width(b"\t\t\n"), 16);
+        // Wide characters take two columns, combining marks none
+        assert_eq!(width("漢字x\n".as_bytes()), 5);
+        assert_eq!(width("e\u{301}\n".as_bytes()), 1);
+        // Carriage returns and form feeds start over
+        assert_eq!(width(b"ab\rabc\n"), 3);
+        assert_eq!(width(b"a\x0cbcd\n"), 3);
+        // Control characters and invalid bytes have no width
+        assert_eq!(width(b"a\x01b\xe9\n"), 2);
+    }
+
+    #[test]
+    fn test_block_boundaries() {
+        // Every way of splitting the input into two blocks, including in the
+        // middle of a character or between invalid bytes, counts the same.
+        let text = [
+            "wörds 漢字\tand 🦀s\n".as_bytes(),
+            b"\xe2\x82 x\xf0\x9f\xa6y\r\nend",
+        ]
+        .concat();
+        let expected = scan(&text);
+        for i in 0..=text.len() {
+            for j in i..=text.len() {
+                let mut scanner = Scanner::new(true);
+                scanner.feed(&text[..i]);
+                scanner.feed(&text[i..j]);
+                scanner.feed(&text[j..]);
+                assert_eq!(scanner.counts(), expected, "split at {i} and {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_chunked() {
+        // However the file is split, only at line starts, the chunks add up
+        // to the same counts as the whole.
+        let path = Path::new("tests/inputs/atlamal.txt");
+        let bytes = std::fs::read(path).unwrap();
+        let expected = FileInfo::from(scan(&bytes));
+        for chunks in 1..=8 {
+            let info = count_chunked(path, bytes.len() as u64, chunks, true).unwrap();
+            assert_eq!(info, expected, "{chunks} chunks");
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::args::SortKey;
+use crate::FileInfo;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// How `--group-by` puts files together.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GroupBy {
+    /// By the extension of the file name.
+    Ext,
+    /// By the directory the file is in.
+    Dir,
+    /// By the first N components of the directory the file is in.
+    Depth(usize),
+}
+
+impl GroupBy {
+    pub fn parse(val: &str) -> Result<GroupBy, String> {
+        match val {
+            "ext" => Ok(GroupBy::Ext),
+            "dir" => Ok(GroupBy::Dir),
+            _ => match val.strip_prefix("depth:").map(str::parse) {
+                Some(Ok(depth)) if depth > 0 => Ok(GroupBy::Depth(depth)),
+                _ => Err(format!("unknown grouping '{}'", val)),
+            },
+        }
+    }
+
+    /// The name of the group `filename` belongs to.
+    fn key(&self, path: &Path) -> String {
+        if let GroupBy::Ext = self {
+            return path.extension().map_or("(none)".to_string(), |ext| {
+                ext.to_string_lossy().into_owned()
+            });
+        }
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let components = dir
+            .components()
+            .filter(|component| *component != Component::CurDir);
+        let dir: PathBuf = match self {
+            GroupBy::Depth(depth) => components.take(*depth).collect(),
+            _ => components.collect(),
+        };
+        if dir.as_os_str().is_empty() {
+            ".".to_string()
+        } else {
+            dir.display().to_string()
+        }
+    }
+}
+
+/// Subtotals of the files counted so far, by group.
+pub struct Groups {
+    group_by: GroupBy,
+    subtotals: BTreeMap<String, FileInfo>,
+}
+
+impl Groups {
+    pub fn new(group_by: GroupBy) -> Groups {
+        Groups {
+            group_by,
+            subtotals: BTreeMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &Path, info: &FileInfo) {
+        self.subtotals
+            .entry(self.group_by.key(path))
+            .or_default()
+            .merge(info);
+    }
+
+    /// The groups and their subtotals, by name or else in the order of
+    /// `sort`, with groups that tie in order of their names.
+    pub fn sorted(self, sort: Option<SortKey>, reverse: bool) -> Vec<(String, FileInfo)> {
+        let mut groups: Vec<_> = self.subtotals.into_iter().collect();
+        if let Some(sort) = sort {
+            sort.sort(&mut groups, reverse, |(name, info)| (name.as_str(), info));
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GroupBy;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(GroupBy::parse("ext"), Ok(GroupBy::Ext));
+        assert_eq!(GroupBy::parse("dir"), Ok(GroupBy::Dir));
+        assert_eq!(GroupBy::parse("depth:2"), Ok(GroupBy::Depth(2)));
+        assert!(GroupBy::parse("depth:0").is_err());
+        assert!(GroupBy::parse("depth:").is_err());
+        assert!(GroupBy::parse("size").is_err());
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(GroupBy::Ext.key(Path::new("src/lib.rs")), "rs");
+        assert_eq!(GroupBy::Ext.key(Path::new("archive.tar.gz")), "gz");
+        assert_eq!(GroupBy::Ext.key(Path::new("Makefile")), "(none)");
+        assert_eq!(GroupBy::Ext.key(Path::new(".gitignore")), "(none)");
+
+        assert_eq!(GroupBy::Dir.key(Path::new("src/a/b.rs")), "src/a");
+        assert_eq!(GroupBy::Dir.key(Path::new("./src/lib.rs")), "src");
+        assert_eq!(GroupBy::Dir.key(Path::new("README.md")), ".");
+
+        assert_eq!(GroupBy::Depth(1).key(Path::new("src/a/b.rs")), "src");
+        assert_eq!(GroupBy::Depth(2).key(Path::new("src/a/b/c.rs")), "src/a");
+        assert_eq!(GroupBy::Depth(3).key(Path::new("src/a.rs")), "src");
+        assert_eq!(GroupBy::Depth(1).key(Path::new("README.md")), ".");
+    }
+}
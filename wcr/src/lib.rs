@@ -1,56 +1,294 @@
 mod args;
+mod code;
+mod counter;
+mod engine;
+mod groups;
+mod records;
+mod segment;
+mod stats;
+mod watch;
 
 pub use args::get_args;
-use args::Config;
-
+use args::{Config, SortKey, Total, WordMode};
+use code::Classifier;
+pub use counter::{count, count_lines, Counter, Counts, Metric};
+use engine::Lines;
+use groups::Groups;
+use records::Records;
+use segment::Segmenter;
+use stats::{LineStats, StatsCounter, Summary};
+
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
+/// What wcr counts of a file, a group or the total: the [`Counts`] of the
+/// library, and those that only its options take, such as `--code`.
 #[derive(Debug, PartialEq, Default)]
 pub struct FileInfo {
     num_lines: usize,
     num_words: usize,
     num_bytes: usize,
     num_chars: usize,
+    num_graphemes: usize,
+    max_line_length: usize,
+    num_blank: usize,
+    num_comment: usize,
+    num_code: usize,
+    line_stats: LineStats,
 }
 
-#[derive(Default)]
-struct Total {
-    lines: usize,
-    words: usize,
-    bytes: usize,
-    chars: usize,
+impl FileInfo {
+    /// Adds the counts of `other`, as for a total or for the chunks of a
+    /// file: the longest line is the longer of the two, the rest are sums.
+    fn merge(&mut self, other: &FileInfo) {
+        self.num_lines += other.num_lines;
+        self.num_words += other.num_words;
+        self.num_bytes += other.num_bytes;
+        self.num_chars += other.num_chars;
+        self.num_graphemes += other.num_graphemes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+        self.num_blank += other.num_blank;
+        self.num_comment += other.num_comment;
+        self.num_code += other.num_code;
+        self.line_stats.merge(&other.line_stats);
+    }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let mut total = Total {
-        ..Default::default()
+impl From<Counts> for FileInfo {
+    fn from(counts: Counts) -> FileInfo {
+        FileInfo {
+            num_lines: counts.lines,
+            num_words: counts.words,
+            num_bytes: counts.bytes,
+            num_chars: counts.chars,
+            max_line_length: counts.max_line_length,
+            ..Default::default()
+        }
+    }
+}
+
+/// Counts and prints every input in `config`, failing if any of them could
+/// not be counted, as coreutils' wc does, though the rest are still counted.
+pub fn run(config: Config) -> MyResult<ExitCode> {
+    if config.watch {
+        watch::watch(&config)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut printer = Printer {
+        total: FileInfo {
+            ..Default::default()
+        },
+        records: Records::new(config.output_format, &config)?,
+        width: column_width(&config),
+        groups: config.group_by.map(Groups::new),
+        rows: match (config.sort, config.group_by) {
+            (Some(_), None) => Some(Vec::new()),
+            _ => None,
+        },
+        failed: config.input_failed,
     };
 
-    for filename in &config.files {
-        match open(filename) {
-            Err(error) => eprintln!("{}: {}", filename, error),
-            Ok(file) => match count(file) {
-                Err(error) => eprintln!("{}: {}", filename, error),
-                Ok(info) => {
-                    print_result(&info, &config, filename);
-                    add_total(&mut total, &info);
+    if config.jobs > 1 && config.files.len() > 1 {
+        run_parallel(&config, &mut printer)?;
+    } else {
+        for filename in &config.files {
+            printer.file(count_input(filename, &config), filename, &config)?;
+        }
+    }
+
+    if let (Some(mut rows), Some(sort)) = (printer.rows.take(), config.sort) {
+        sort.sort(&mut rows, config.reverse, |(filename, info, _)| {
+            (filename.as_str(), info)
+        });
+        for (filename, info, error) in rows {
+            printer.row(&filename, Some(&info), error.as_deref(), &config)?;
+        }
+    }
+
+    if let Some(groups) = printer.groups.take() {
+        if config.total != Total::Only {
+            for (name, info) in groups.sorted(config.sort, config.reverse) {
+                match &mut printer.records {
+                    Some(records) => records.group(&name, &info)?,
+                    None => print_result(&info, &config, &name, printer.width),
                 }
-            },
+            }
         }
     }
 
-    if config.files.len() > 1 {
-        print_total(&total, &config);
+    let show_total = match config.total {
+        Total::Auto => config.files.len() > 1,
+        Total::Always | Total::Only => true,
+        Total::Never => false,
+    };
+    match printer.records {
+        Some(mut records) => {
+            if show_total {
+                records.total(&printer.total)?;
+            }
+            records.finish()?;
+        }
+        None => {
+            if show_total {
+                print_total(&printer.total, &config, printer.width);
+            }
+            if config.histogram {
+                print_histogram(&printer.total.line_stats);
+            }
+        }
     }
 
-    Ok(())
+    Ok(if printer.failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Prints each file's counts, as a table or as records, and keeps their
+/// total and, with `--group-by`, the subtotals of each group instead. With
+/// `--sort`, the files' counts are kept in `rows` to be printed in order once
+/// all of them are counted.
+struct Printer {
+    total: FileInfo,
+    records: Option<Records>,
+    width: usize,
+    groups: Option<Groups>,
+    rows: Option<Vec<(String, FileInfo, Option<String>)>>,
+    failed: bool,
+}
+
+impl Printer {
+    fn file(
+        &mut self,
+        result: Result<FileInfo, Failure>,
+        path: &Path,
+        config: &Config,
+    ) -> io::Result<()> {
+        let filename = path.to_string_lossy();
+        let (info, error) = match result {
+            Ok(info) => (Some(info), None),
+            Err(Failure { message, opened }) => {
+                eprintln!("wcr: {}: {}", filename, message);
+                self.failed = true;
+                (opened.then(FileInfo::default), Some(message))
+            }
+        };
+        if let Some(info) = &info {
+            if !selected(info, config) {
+                return Ok(());
+            }
+            self.total.merge(info);
+        }
+
+        if let (Some(info), Some(groups)) = (&info, &mut self.groups) {
+            groups.add(path, info);
+        } else if config.total != Total::Only {
+            match (info, &mut self.rows) {
+                (Some(info), Some(rows)) => rows.push((filename.to_string(), info, error)),
+                (info, _) => self.row(&filename, info.as_ref(), error.as_deref(), config)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn row(
+        &mut self,
+        filename: &str,
+        info: Option<&FileInfo>,
+        error: Option<&str>,
+        config: &Config,
+    ) -> io::Result<()> {
+        match (&mut self.records, info) {
+            (Some(records), _) => records.file(filename, info, error)?,
+            (None, Some(info)) => print_result(info, config, filename, self.width),
+            (None, None) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Why a file could not be counted. An error reading a file, rather than
+/// opening it, still leaves a row of counts, all zero, as GNU wc prints for
+/// a directory.
+struct Failure {
+    message: String,
+    opened: bool,
+}
+
+fn count_input(filename: &Path, config: &Config) -> Result<FileInfo, Failure> {
+    let file = open(filename).map_err(|e| Failure {
+        message: describe(&*e),
+        opened: false,
+    })?;
+    count_file(file, filename, config).map_err(|e| Failure {
+        message: describe(&*e),
+        opened: true,
+    })
+}
+
+/// The message of `error`, less the " (os error N)" of I/O errors.
+pub(crate) fn describe(error: &dyn Error) -> String {
+    let message = error.to_string();
+    match message.find(" (os error ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+/// Whether a file is kept by `--min-lines` and `--max-bytes`.
+fn selected(info: &FileInfo, config: &Config) -> bool {
+    config.min_lines.is_none_or(|min| info.num_lines >= min)
+        && config.max_bytes.is_none_or(|max| info.num_bytes <= max)
+}
+
+/// Counts files on `config.jobs` threads, printing each one's counts as soon
+/// as every file before it has been printed.
+fn run_parallel(config: &Config, printer: &mut Printer) -> io::Result<()> {
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..config.jobs.min(config.files.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(filename) = config.files.get(index) else {
+                    break;
+                };
+                let result = count_input(filename, config);
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut printed = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&printed) {
+                printer.file(result, &config.files[printed], config)?;
+                printed += 1;
+            }
+        }
+        Ok(())
+    })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
+fn open(filename: &Path) -> MyResult<Box<dyn BufRead>> {
     // let reader: Box<dyn BufRead> = if filename == "-" {
     //     Box::new(BufReader::new(io::stdin()))
     // } else {
@@ -58,42 +296,79 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     // };
     // Ok(reader)
 
-    Ok(match filename {
-        "-" => Box::new(BufReader::new(io::stdin())),
+    Ok(match filename.to_str() {
+        Some("-") => Box::new(BufReader::new(io::stdin())),
         _ => Box::new(BufReader::new(File::open(filename)?)),
     })
 }
 
-fn count<R: BufRead>(mut file: R) -> MyResult<FileInfo> {
-    let mut info = FileInfo {
-        ..Default::default()
-    };
-    let mut line = String::new();
-
-    while file.read_line(&mut line)? > 0 {
-        info.num_lines += 1;
-        info.num_bytes += line.len();
-        info.num_words += line.split_whitespace().count();
-        info.num_chars += line.chars().count();
-        line.clear();
+/// Counts `file` doing no more work than `config` needs: a regular file's
+/// size is taken from its metadata when only bytes are wanted, and the input
+/// is only decoded when words, characters or line widths are wanted, whether
+/// to be printed or to sort or leave out files by. A large file counted on
+/// its own is split into chunks counted on `config.jobs` threads, unless it
+/// is also counted a line at a time, for `--code`, Unicode words, graphemes
+/// or line statistics, which needs reading it from the start.
+fn count_file(file: Box<dyn BufRead>, filename: &Path, config: &Config) -> MyResult<FileInfo> {
+    let sort = |keys: &[SortKey]| config.sort.is_some_and(|sort| keys.contains(&sort));
+    let decode = config.words
+        || config.chars
+        || config.max_line_length
+        || sort(&[SortKey::Words, SortKey::Chars, SortKey::MaxLineLength]);
+    let lines = config.lines || config.min_lines.is_some() || sort(&[SortKey::Lines]);
+    let unicode_words = config.words && config.word_mode == WordMode::Unicode;
+    let mut classifier = config.code.then(|| Lines::new(Classifier::new(filename)));
+    let mut segmenter = (unicode_words || config.graphemes)
+        .then(|| Lines::new(Segmenter::new(unicode_words, config.graphemes)));
+    let mut stats = (config.stats || config.histogram).then(|| Lines::new(StatsCounter::new()));
+    let by_line = classifier.is_some() || segmenter.is_some() || stats.is_some();
+
+    if filename.as_os_str() != "-" && !by_line {
+        let metadata = fs::metadata(filename)?;
+        if metadata.is_dir() {
+            return Err(From::from("Is a directory"));
+        }
+        // Files in /proc and the like claim to be empty, so read those
+        if metadata.is_file() && metadata.len() > 0 {
+            if !decode && !lines {
+                return Ok(FileInfo {
+                    num_bytes: metadata.len() as usize,
+                    ..Default::default()
+                });
+            }
+
+            let chunks = (metadata.len() / engine::MIN_CHUNK_SIZE).min(config.jobs as u64);
+            if config.files.len() == 1 && chunks > 1 {
+                return engine::count_chunked(filename, metadata.len(), chunks, decode);
+            }
+        }
     }
 
+    // Counts taken a line at a time are taken in the same pass as the rest
+    let mut counter = Counter::decoding(decode);
+    if let Some(classifier) = &mut classifier {
+        counter = counter.with_metric(classifier);
+    }
+    if let Some(segmenter) = &mut segmenter {
+        counter = counter.with_metric(segmenter);
+    }
+    if let Some(stats) = &mut stats {
+        counter = counter.with_metric(stats);
+    }
+    counter.read_from(file)?;
+    let mut info = FileInfo::from(counter.finish());
+    if unicode_words {
+        info.num_words = 0;
+    }
+    let line_counts = [
+        classifier.map(Lines::into_counts),
+        segmenter.map(Lines::into_counts),
+        stats.map(Lines::into_counts),
+    ];
+    for counts in line_counts.into_iter().flatten() {
+        info.merge(&counts);
+    }
     Ok(info)
-
-    // let mut contents = String::new();
-    // file.read_to_string(&mut contents)?;
-
-    // let num_lines = contents.lines().count();
-    // let num_words = contents.split_whitespace().count();
-    // let num_bytes = contents.len();
-    // let num_chars = contents.chars().count();
-
-    // Ok(FileInfo {
-    //     num_lines,
-    //     num_words,
-    //     num_bytes,
-    //     num_chars,
-    // })
 }
 
 fn format_field(value: usize, show: bool) -> String {
@@ -104,46 +379,166 @@ fn format_field(value: usize, show: bool) -> String {
     }
 }
 
-fn print_result(info: &FileInfo, config: &Config, filename: &str) {
+/// Lays out the counts that `config` asks for, each right-aligned to
+/// `width` and separated by a space like GNU wc, or with `--fixed-width`
+/// each 8 wide and not separated.
+pub(crate) fn format_counts(info: &FileInfo, config: &Config, width: usize) -> String {
+    let fields = [
+        (info.num_lines, config.lines),
+        (info.num_words, config.words),
+        (info.num_bytes, config.bytes),
+        (info.num_chars, config.chars),
+        (info.num_graphemes, config.graphemes),
+        (info.max_line_length, config.max_line_length),
+        (info.num_blank, config.code),
+        (info.num_comment, config.code),
+        (info.num_code, config.code),
+    ];
+    if config.fixed_width {
+        return fields
+            .iter()
+            .map(|&(value, show)| format_field(value, show))
+            .collect();
+    }
+    fields
+        .iter()
+        .filter(|(_, show)| *show)
+        .map(|(value, _)| format!("{:>width$}", value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The width GNU wc gives every column, known before anything is counted:
+/// enough for the total size of the regular files, but at least 7 when the
+/// size of some input is unknown. A single count of a single input is not
+/// padded at all.
+fn column_width(config: &Config) -> usize {
+    let columns = [
+        config.lines,
+        config.words,
+        config.bytes,
+        config.chars,
+        config.graphemes,
+        config.max_line_length,
+        config.code,
+        config.code,
+        config.code,
+    ];
+    if config.files.len() == 1 && columns.iter().filter(|&&show| show).count() == 1 {
+        return 1;
+    }
+
+    let mut minimum = 1;
+    let mut total_size = 0;
+    for filename in &config.files {
+        match input_metadata(filename) {
+            Ok(metadata) if metadata.is_file() => total_size += metadata.len(),
+            Ok(_) => minimum = 7,
+            Err(_) => {}
+        }
+    }
+    total_size.to_string().len().max(minimum)
+}
+
+#[cfg(unix)]
+fn input_metadata(filename: &Path) -> io::Result<fs::Metadata> {
+    use std::os::fd::AsFd;
+
+    match filename.to_str() {
+        Some("-") => File::from(io::stdin().as_fd().try_clone_to_owned()?).metadata(),
+        _ => fs::metadata(filename),
+    }
+}
+
+#[cfg(not(unix))]
+fn input_metadata(filename: &Path) -> io::Result<fs::Metadata> {
+    match filename.to_str() {
+        Some("-") => Err(io::ErrorKind::Unsupported.into()),
+        _ => fs::metadata(filename),
+    }
+}
+
+fn print_result(info: &FileInfo, config: &Config, filename: &str, width: usize) {
     println!(
-        "{}{}{}{}{}",
-        format_field(info.num_lines, config.lines),
-        format_field(info.num_words, config.words),
-        format_field(info.num_bytes, config.bytes),
-        format_field(info.num_chars, config.chars),
+        "{}{}",
+        format_counts(info, config, width),
         match filename {
             "-" => "".to_string(),
             _ => format!(" {}", filename),
         }
     );
+    if config.stats {
+        print_stats(info);
+    }
 }
 
-fn print_total(total: &Total, config: &Config) {
+fn print_total(total: &FileInfo, config: &Config, width: usize) {
     println!(
-        "{}{}{}{} total",
-        format_field(total.lines, config.lines),
-        format_field(total.words, config.words),
-        format_field(total.bytes, config.bytes),
-        format_field(total.chars, config.chars),
-    )
-}
-
-fn add_total(total: &mut Total, info: &FileInfo) {
-    // *total = Total {
-    //     lines: total.lines + info.num_lines,
-    //     words: total.words + info.num_words,
-    //     bytes: total.bytes + info.num_bytes,
-    //     chars: total.chars + info.num_chars,
-    // }
-    total.lines += info.num_lines;
-    total.words += info.num_words;
-    total.bytes += info.num_bytes;
-    total.chars += info.num_chars;
+        "{}{}",
+        format_counts(total, config, width),
+        match config.total {
+            Total::Only => "",
+            _ => " total",
+        }
+    );
+    if config.stats {
+        print_stats(total);
+    }
+}
+
+/// Prints the spread of line lengths and of words per line under a row of
+/// counts for `--stats`.
+fn print_stats(info: &FileInfo) {
+    let summaries = [
+        ("line length", info.line_stats.lengths()),
+        ("words per line", info.line_stats.words()),
+    ];
+    for (name, summary) in summaries {
+        if let Some(Summary {
+            min,
+            max,
+            mean,
+            median,
+            p95,
+        }) = summary
+        {
+            println!(
+                "  {:>14}: min {}, max {}, mean {:.1}, median {}, p95 {}",
+                name, min, max, mean, median, p95
+            );
+        }
+    }
+}
+
+/// Prints how many lines there are of each length for `--histogram`, with a
+/// bar of up to 40 `#` for each bucket of lengths.
+fn print_histogram(stats: &LineStats) {
+    let buckets = stats.histogram();
+    let most = buckets
+        .iter()
+        .map(|&(_, _, lines)| lines)
+        .max()
+        .unwrap_or(0);
+    let labels: Vec<_> = buckets
+        .iter()
+        .map(|&(first, last, _)| match first == last {
+            true => first.to_string(),
+            false => format!("{}-{}", first, last),
+        })
+        .collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let lines_width = most.to_string().len();
+
+    for (label, &(_, _, lines)) in labels.iter().zip(&buckets) {
+        let bar = "#".repeat((lines * 40).div_ceil(most));
+        let row = format!("{:>label_width$} {:>lines_width$} {}", label, lines, bar);
+        println!("{}", row.trim_end());
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{count, format_field, FileInfo};
+    use super::{count, format_field, Counts};
     use std::io::Cursor;
 
     #[test]
@@ -151,11 +546,30 @@ mod tests {
         let text = "I don't want the world. I just want your half.\r\n";
         let info = count(Cursor::new(text));
         assert!(info.is_ok());
-        let expected = FileInfo {
-            num_lines: 1,
-            num_words: 10,
-            num_bytes: 48,
-            num_chars: 48,
+        let expected = Counts {
+            lines: 1,
+            words: 10,
+            bytes: 48,
+            chars: 48,
+            max_line_length: 46,
+        };
+
+        assert_eq!(info.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        // Invalid bytes are neither characters nor word separators, and a
+        // last line without a newline is not a line.
+        let text: &[u8] = b"caf\xe9 au lait\n\xff\xfe\nend";
+        let info = count(Cursor::new(text));
+        assert!(info.is_ok());
+        let expected = Counts {
+            lines: 2,
+            words: 4,
+            bytes: 19,
+            chars: 16,
+            max_line_length: 11,
         };
 
         assert_eq!(info.unwrap(), expected);
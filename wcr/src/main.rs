@@ -1,6 +1,11 @@
-fn main() {
-    if let Err(e) = wcr::get_args().and_then(wcr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match wcr::get_args().and_then(wcr::run) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("wcr: {}", error);
+            ExitCode::FAILURE
+        }
     }
 }
@@ -0,0 +1,234 @@
+use crate::args::Config;
+use crate::FileInfo;
+use serde_json::{Map, Value};
+use std::io::{self, BufWriter, Stdout, Write};
+
+/// Formats understood by `--output-format`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// Right-aligned columns, as GNU wc prints them.
+    Table,
+    /// A JSON array of records.
+    Json,
+    /// One JSON record per line.
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn parse(val: &str) -> Result<Format, String> {
+        match val {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("unknown format '{}'", val)),
+        }
+    }
+}
+
+/// The fields that `--stats` adds, for line lengths and then for words per
+/// line.
+const STATS_COLUMNS: [&str; 10] = [
+    "line_length_min",
+    "line_length_max",
+    "line_length_mean",
+    "line_length_median",
+    "line_length_p95",
+    "words_per_line_min",
+    "words_per_line_max",
+    "words_per_line_mean",
+    "words_per_line_median",
+    "words_per_line_p95",
+];
+
+/// Writes a record for each file or group and for the total in one of the
+/// machine-readable formats. Every record has the same fields, in the same
+/// order whatever the options: `type` ("file", "group" or "total"), `name`,
+/// every count, the fields of `--stats` only when it is given, and `error`.
+/// The fields that do not apply, such as counts that were not asked for, are
+/// null, or empty in CSV and TSV.
+pub struct Records {
+    /// Each field after `name`, and whether it was asked for.
+    columns: Vec<(&'static str, bool)>,
+    sink: Sink,
+}
+
+enum Sink {
+    Json {
+        out: BufWriter<Stdout>,
+        array: bool,
+        written: usize,
+    },
+    Delimited(Box<csv::Writer<Stdout>>),
+}
+
+impl Records {
+    /// Returns `None` for `Format::Table`, which is printed as before.
+    pub fn new(format: Format, config: &Config) -> io::Result<Option<Records>> {
+        let columns = [
+            ("lines", config.lines),
+            ("words", config.words),
+            ("bytes", config.bytes),
+            ("chars", config.chars),
+            ("graphemes", config.graphemes),
+            ("max_line_length", config.max_line_length),
+            ("blank", config.code),
+            ("comment", config.code),
+            ("code", config.code),
+        ]
+        .into_iter()
+        .chain(
+            STATS_COLUMNS
+                .into_iter()
+                .filter(|_| config.stats)
+                .map(|name| (name, true)),
+        )
+        .collect();
+
+        let json = |array| Sink::Json {
+            out: BufWriter::new(io::stdout()),
+            array,
+            written: 0,
+        };
+        let delimited = |delimiter| {
+            Sink::Delimited(Box::new(
+                csv::WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .from_writer(io::stdout()),
+            ))
+        };
+        let sink = match format {
+            Format::Table => return Ok(None),
+            Format::Json => json(true),
+            Format::Jsonl => json(false),
+            Format::Csv => delimited(b','),
+            Format::Tsv => delimited(b'\t'),
+        };
+
+        let mut records = Records { columns, sink };
+        if let Sink::Delimited(writer) = &mut records.sink {
+            let mut header = vec!["type", "name"];
+            header.extend(records.columns.iter().map(|&(name, _)| name));
+            header.push("error");
+            writer.write_record(header)?;
+        }
+        Ok(Some(records))
+    }
+
+    /// Writes the record of a file, which has counts unless it could not be
+    /// opened, and an error if it could not be counted.
+    pub fn file(
+        &mut self,
+        filename: &str,
+        info: Option<&FileInfo>,
+        error: Option<&str>,
+    ) -> io::Result<()> {
+        self.write("file", Some(filename), info, error)
+    }
+
+    pub fn group(&mut self, name: &str, subtotal: &FileInfo) -> io::Result<()> {
+        self.write("group", Some(name), Some(subtotal), None)
+    }
+
+    pub fn total(&mut self, total: &FileInfo) -> io::Result<()> {
+        self.write("total", None, Some(total), None)
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.sink {
+            Sink::Json {
+                mut out,
+                array,
+                written,
+            } => {
+                if array {
+                    out.write_all(if written == 0 { b"[]\n" } else { b"\n]\n" })?;
+                }
+                out.flush()
+            }
+            Sink::Delimited(mut writer) => writer.flush(),
+        }
+    }
+
+    fn write(
+        &mut self,
+        kind: &str,
+        name: Option<&str>,
+        info: Option<&FileInfo>,
+        error: Option<&str>,
+    ) -> io::Result<()> {
+        let mut record = Map::new();
+        record.insert("type".to_string(), kind.into());
+        record.insert("name".to_string(), name.into());
+        for &(column, show) in &self.columns {
+            let value = match info {
+                Some(info) if show => field(column, info),
+                _ => Value::Null,
+            };
+            record.insert(column.to_string(), value);
+        }
+        record.insert("error".to_string(), error.into());
+
+        match &mut self.sink {
+            Sink::Json {
+                out,
+                array,
+                written,
+            } => {
+                if *array {
+                    out.write_all(if *written == 0 { b"[\n" } else { b",\n" })?;
+                }
+                serde_json::to_writer(&mut *out, &record)?;
+                if !*array {
+                    out.write_all(b"\n")?;
+                }
+                *written += 1;
+            }
+            Sink::Delimited(writer) => {
+                writer.write_record(record.values().map(|value| match value {
+                    Value::Null => String::new(),
+                    Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                }))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The value of the field `column` for `info`.
+fn field(column: &str, info: &FileInfo) -> Value {
+    let count = match column {
+        "lines" => info.num_lines,
+        "words" => info.num_words,
+        "bytes" => info.num_bytes,
+        "chars" => info.num_chars,
+        "graphemes" => info.num_graphemes,
+        "max_line_length" => info.max_line_length,
+        "blank" => info.num_blank,
+        "comment" => info.num_comment,
+        "code" => info.num_code,
+        _ => return stats_field(column, info),
+    };
+    count.into()
+}
+
+/// The value of one of [`STATS_COLUMNS`], which is null for a file with no
+/// lines.
+fn stats_field(column: &str, info: &FileInfo) -> Value {
+    let (summary, stat) = match column.strip_prefix("line_length_") {
+        Some(stat) => (info.line_stats.lengths(), stat),
+        None => (info.line_stats.words(), &column["words_per_line_".len()..]),
+    };
+    summary.map_or(Value::Null, |summary| match stat {
+        "min" => summary.min.into(),
+        "max" => summary.max.into(),
+        // To two places, as more would be noise
+        "mean" => ((summary.mean * 100.0).round() / 100.0).into(),
+        "median" => summary.median.into(),
+        _ => summary.p95.into(),
+    })
+}
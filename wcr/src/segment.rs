@@ -0,0 +1,81 @@
+use crate::engine::LineCounter;
+use crate::FileInfo;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Counts words by their Unicode (UAX #29) boundaries rather than by white
+/// space, and extended grapheme clusters, the characters a reader sees. A
+/// line is segmented whole, newline and all, as no word or grapheme spans a
+/// line break, and invalid bytes are skipped as by the other counts.
+pub struct Segmenter {
+    words: bool,
+    graphemes: bool,
+    counts: FileInfo,
+}
+
+impl Segmenter {
+    pub fn new(words: bool, graphemes: bool) -> Segmenter {
+        Segmenter {
+            words,
+            graphemes,
+            counts: FileInfo::default(),
+        }
+    }
+}
+
+impl LineCounter for Segmenter {
+    fn line(&mut self, line: &[u8]) {
+        for chunk in line.utf8_chunks() {
+            let text = chunk.valid();
+            if self.words {
+                self.counts.num_words += text.unicode_words().count();
+            }
+            if self.graphemes {
+                self.counts.num_graphemes += text.graphemes(true).count();
+            }
+        }
+    }
+
+    fn finish(self) -> FileInfo {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Segmenter;
+    use crate::engine::Lines;
+    use crate::Metric;
+
+    /// The (words, graphemes) counts of `text`, fed `size` bytes at a time.
+    fn segment(text: &str, size: usize) -> (usize, usize) {
+        let mut segmenter = Lines::new(Segmenter::new(true, true));
+        for block in text.as_bytes().chunks(size) {
+            segmenter.update(block);
+        }
+        let counts = segmenter.into_counts();
+        (counts.num_words, counts.num_graphemes)
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            segment(
+                "The quick (\"brown\") fox can't jump 32.3 feet, right?\n",
+                64
+            )
+            .0,
+            9
+        );
+        // Each ideograph is a word of its own, but katakana run together
+        assert_eq!(segment("日本語の文章です。\nコンピューター\n", 64).0, 9);
+    }
+
+    #[test]
+    fn test_graphemes() {
+        // A combining accent, a flag, a family of four and CR LF
+        let text = "cafe\u{301} 🇯🇵 👨‍👩‍👧‍👦\r\n";
+        assert_eq!(segment(text, 64), (1, 9));
+        // Split between blocks in the middle of every cluster
+        assert_eq!(segment(text, 1), (1, 9));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::engine::{self, LineCounter};
+use crate::FileInfo;
+use std::collections::BTreeMap;
+
+/// How many lines have each display width and each number of words, kept
+/// whole so that the statistics of several files can be drawn from their
+/// merged counts.
+#[derive(Debug, PartialEq, Default)]
+pub struct LineStats {
+    lengths: BTreeMap<usize, usize>,
+    words: BTreeMap<usize, usize>,
+}
+
+/// The spread of a count over the lines of a file, with the median and 95th
+/// percentile by nearest rank.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub p95: usize,
+}
+
+impl LineStats {
+    pub fn merge(&mut self, other: &LineStats) {
+        for (&length, &lines) in &other.lengths {
+            *self.lengths.entry(length).or_default() += lines;
+        }
+        for (&words, &lines) in &other.words {
+            *self.words.entry(words).or_default() += lines;
+        }
+    }
+
+    /// The summary of line lengths, as display widths like `-L`, or `None`
+    /// if there are no lines.
+    pub fn lengths(&self) -> Option<Summary> {
+        Summary::of(&self.lengths)
+    }
+
+    pub fn words(&self) -> Option<Summary> {
+        Summary::of(&self.words)
+    }
+
+    /// The number of lines of each length in buckets of 0, 1, 2-3, 4-7 and
+    /// so on, each twice as wide as the last, from the shortest line's
+    /// bucket to the longest's, as (first length, last length, lines).
+    pub fn histogram(&self) -> Vec<(usize, usize, usize)> {
+        let bucket = |length: usize| (usize::BITS - length.leading_zeros()) as usize;
+        let (Some(&first), Some(&last)) = (self.lengths.keys().next(), self.lengths.keys().last())
+        else {
+            return vec![];
+        };
+
+        let mut buckets: Vec<_> = (bucket(first)..=bucket(last))
+            .map(|i| match i {
+                0 => (0, 0, 0),
+                _ => (1 << (i - 1), (1 << i) - 1, 0),
+            })
+            .collect();
+        for (&length, &lines) in &self.lengths {
+            buckets[bucket(length) - bucket(first)].2 += lines;
+        }
+        buckets
+    }
+}
+
+impl Summary {
+    fn of(counts: &BTreeMap<usize, usize>) -> Option<Summary> {
+        let lines: usize = counts.values().sum();
+        if lines == 0 {
+            return None;
+        }
+        let sum: usize = counts.iter().map(|(value, n)| value * n).sum();
+        Some(Summary {
+            min: *counts.keys().next()?,
+            max: *counts.keys().last()?,
+            mean: sum as f64 / lines as f64,
+            median: percentile(counts, lines, 50),
+            p95: percentile(counts, lines, 95),
+        })
+    }
+}
+
+/// The least value that at least `percent` percent of the `lines` counted
+/// in `counts` are no more than.
+fn percentile(counts: &BTreeMap<usize, usize>, lines: usize, percent: usize) -> usize {
+    let rank = (lines * percent).div_ceil(100).max(1);
+    let mut seen = 0;
+    for (&value, &n) in counts {
+        seen += n;
+        if seen >= rank {
+            return value;
+        }
+    }
+    0
+}
+
+/// Takes the length and number of words of each line for `--stats` and
+/// `--histogram`.
+pub struct StatsCounter {
+    stats: LineStats,
+}
+
+impl StatsCounter {
+    pub fn new() -> StatsCounter {
+        StatsCounter {
+            stats: LineStats::default(),
+        }
+    }
+}
+
+impl LineCounter for StatsCounter {
+    fn line(&mut self, line: &[u8]) {
+        let counts = engine::count_line(line);
+        *self
+            .stats
+            .lengths
+            .entry(counts.max_line_length)
+            .or_default() += 1;
+        *self.stats.words.entry(counts.words).or_default() += 1;
+    }
+
+    fn finish(self) -> FileInfo {
+        FileInfo {
+            line_stats: self.stats,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatsCounter, Summary};
+    use crate::engine::Lines;
+    use crate::FileInfo;
+    use crate::Metric;
+
+    fn stats(text: &str) -> FileInfo {
+        let mut counter = Lines::new(StatsCounter::new());
+        counter.update(text.as_bytes());
+        counter.into_counts()
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut info = stats("one two\n\nthree\tfour five\nsix\n");
+        assert_eq!(
+            info.line_stats.lengths(),
+            Some(Summary {
+                min: 0,
+                max: 17,
+                mean: 6.75,
+                median: 3,
+                p95: 17,
+            })
+        );
+        assert_eq!(
+            info.line_stats.words(),
+            Some(Summary {
+                min: 0,
+                max: 3,
+                mean: 1.5,
+                median: 1,
+                p95: 3,
+            })
+        );
+
+        info.merge(&stats("x\n".repeat(95).as_str()));
+        let lengths = info.line_stats.lengths().unwrap();
+        assert_eq!((lengths.median, lengths.p95), (1, 1));
+        assert_eq!(stats("").line_stats.lengths(), None);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let info = stats("abc\nabcd\nabcdefgh\nab\n");
+        assert_eq!(
+            info.line_stats.histogram(),
+            vec![(2, 3, 2), (4, 7, 1), (8, 15, 1)]
+        );
+        let info = stats("\na\n");
+        assert_eq!(info.line_stats.histogram(), vec![(0, 0, 1), (1, 1, 1)]);
+        assert_eq!(stats("").line_stats.histogram(), vec![]);
+    }
+}
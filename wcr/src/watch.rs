@@ -0,0 +1,222 @@
+use crate::args::Config;
+use crate::engine::BLOCK_SIZE;
+use crate::{describe, format_counts, Counter, FileInfo, MyResult};
+use serde_json::Map;
+use std::fs::File;
+use std::io::{self, ErrorKind, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a followed file is checked for having grown once all of it has
+/// been read.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What the thread reading the input sends on.
+enum Event {
+    Block(Vec<u8>),
+    /// The followed file got shorter, so it is read again from the start.
+    Truncated,
+}
+
+/// Counts the one input in `config.files` as it is read, every
+/// `config.interval` writing the counts so far and the rates of lines and
+/// bytes since the last time: on a single status line that is rewritten in
+/// place when the standard output is a terminal, or else as a JSON object
+/// on a line of its own. A regular file is followed as it grows, like
+/// `tail -f`, until interrupted; anything else is counted until it ends.
+pub fn watch(config: &Config) -> MyResult<()> {
+    let [filename] = config.files.as_slice() else {
+        return Err(From::from(
+            "--watch counts a single file or the standard input",
+        ));
+    };
+    let decode = config.words || config.chars || config.max_line_length;
+
+    let (sender, receiver) = mpsc::channel();
+    let name = filename.to_string_lossy();
+    let input = match filename.to_str() {
+        Some("-") => None,
+        _ => Some(File::open(filename).map_err(|e| format!("{}: {}", name, describe(&e)))?),
+    };
+    let follow = match &input {
+        Some(file) => file.metadata()?.is_file(),
+        None => false,
+    };
+    thread::spawn(move || {
+        let result = match input {
+            Some(file) if follow => read_following(file, &sender),
+            Some(file) => read(file, &sender),
+            None => read(io::stdin().lock(), &sender),
+        };
+        if let Err(e) = result {
+            let _ = sender.send(Err(e));
+        }
+    });
+
+    let mut snapshots = Snapshots {
+        config,
+        filename: &name,
+        tty: io::stdout().is_terminal(),
+        start: Instant::now(),
+        last: (Instant::now(), FileInfo::default()),
+    };
+    let mut counter = Counter::decoding(decode);
+    let mut next = snapshots.start + config.interval;
+    loop {
+        match receiver.recv_timeout(next.saturating_duration_since(Instant::now())) {
+            Ok(Ok(Event::Block(block))) => counter.update(&block),
+            Ok(Ok(Event::Truncated)) => counter = Counter::decoding(decode),
+            Ok(Err(e)) => {
+                snapshots.end()?;
+                return Err(From::from(format!("{}: {}", name, describe(&e))));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        if now >= next {
+            snapshots.write(&FileInfo::from(counter.counts()), now)?;
+            while next <= now {
+                next += config.interval;
+            }
+        }
+    }
+
+    snapshots.write(&FileInfo::from(counter.counts()), Instant::now())?;
+    Ok(snapshots.end()?)
+}
+
+/// Sends what `input` has to `sender` until it ends.
+fn read<R: Read>(mut input: R, sender: &Sender<io::Result<Event>>) -> io::Result<()> {
+    let mut block = vec![0; BLOCK_SIZE];
+    loop {
+        match input.read(&mut block) {
+            Ok(0) => return Ok(()),
+            Ok(n) => {
+                if sender.send(Ok(Event::Block(block[..n].to_vec()))).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Sends what `file` has to `sender`, then waits for more to be written to
+/// it, for as long as anything receives it.
+fn read_following(mut file: File, sender: &Sender<io::Result<Event>>) -> io::Result<()> {
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut pos = 0;
+    loop {
+        let event = match file.read(&mut block) {
+            Ok(0) => {
+                thread::sleep(POLL_INTERVAL);
+                if file.metadata()?.len() >= pos {
+                    continue;
+                }
+                file.seek(SeekFrom::Start(0))?;
+                pos = 0;
+                Event::Truncated
+            }
+            Ok(n) => {
+                pos += n as u64;
+                Event::Block(block[..n].to_vec())
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if sender.send(Ok(event)).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Writes the counts so far for `--watch`.
+struct Snapshots<'a> {
+    config: &'a Config,
+    filename: &'a str,
+    tty: bool,
+    start: Instant,
+    /// When the last snapshot was taken, and its counts.
+    last: (Instant, FileInfo),
+}
+
+impl Snapshots<'_> {
+    fn write(&mut self, info: &FileInfo, now: Instant) -> io::Result<()> {
+        let (then, last) = &self.last;
+        let seconds = now.duration_since(*then).as_secs_f64();
+        // A truncated file starts its counts over, so they may go down
+        let rate = |count: usize, last: usize| {
+            if seconds > 0.0 {
+                (count.saturating_sub(last) as f64 / seconds).round()
+            } else {
+                0.0
+            }
+        };
+        let lines_per_sec = rate(info.num_lines, last.num_lines);
+        let bytes_per_sec = rate(info.num_bytes, last.num_bytes);
+
+        let mut out = io::stdout().lock();
+        if self.tty {
+            // Back to the start of the line, and clear the rest of it
+            write!(
+                out,
+                "\r{}{} ({} lines/s, {} bytes/s)\x1b[K",
+                format_counts(info, self.config, 1),
+                match self.filename {
+                    "-" => "".to_string(),
+                    name => format!(" {}", name),
+                },
+                lines_per_sec,
+                bytes_per_sec
+            )?;
+        } else {
+            let mut snapshot = Map::new();
+            snapshot.insert("name".to_string(), self.filename.into());
+            let elapsed = now.duration_since(self.start).as_millis() as f64 / 1000.0;
+            snapshot.insert("elapsed".to_string(), elapsed.into());
+            let counts = [
+                ("lines", info.num_lines, self.config.lines),
+                ("words", info.num_words, self.config.words),
+                ("bytes", info.num_bytes, self.config.bytes),
+                ("chars", info.num_chars, self.config.chars),
+                (
+                    "max_line_length",
+                    info.max_line_length,
+                    self.config.max_line_length,
+                ),
+            ];
+            for (name, count, show) in counts {
+                if show {
+                    snapshot.insert(name.to_string(), count.into());
+                }
+            }
+            snapshot.insert("lines_per_sec".to_string(), lines_per_sec.into());
+            snapshot.insert("bytes_per_sec".to_string(), bytes_per_sec.into());
+            serde_json::to_writer(&mut out, &snapshot)?;
+            writeln!(out)?;
+        }
+        out.flush()?;
+
+        self.last = (
+            now,
+            FileInfo {
+                num_lines: info.num_lines,
+                num_bytes: info.num_bytes,
+                ..Default::default()
+            },
+        );
+        Ok(())
+    }
+
+    /// Ends the status line, if any.
+    fn end(&self) -> io::Result<()> {
+        if self.tty {
+            println!();
+        }
+        Ok(())
+    }
+}
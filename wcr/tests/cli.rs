@@ -9,6 +9,11 @@ const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const MULTILINGUAL: &str = "tests/inputs/multilingual.txt";
+const DIR: &str = "tests/inputs/dir";
+const FILES0: &str = "tests/inputs/files0.txt";
+const CODE: &str = "tests/inputs/code";
 
 // --------------------------------------------------
 fn gen_bad_file() -> String {
@@ -54,12 +59,12 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
 #[test]
 fn skips_bad_file() -> Result<()> {
     let bad = gen_bad_file();
-    let expected = format!("{bad}: .* [(]os error 2[)]");
     Command::cargo_bin(PRG)?
-        .arg(bad)
+        .args([&bad, FOX])
         .assert()
-        .success()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .stdout(" 1  9 48 tests/inputs/fox.txt\n 1  9 48 total\n")
+        .stderr(format!("wcr: {bad}: No such file or directory\n"));
     Ok(())
 }
 
@@ -117,6 +122,15 @@ fn fox_bytes_lines() -> Result<()> {
     run(&["-l", "-c", FOX], "tests/expected/fox.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn fox_max_line_length() -> Result<()> {
+    run(
+        &["--max-line-length", FOX],
+        "tests/expected/fox.txt.max.out",
+    )
+}
+
 // --------------------------------------------------
 #[test]
 fn atlamal() -> Result<()> {
@@ -159,6 +173,24 @@ fn atlamal_bytes_lines() -> Result<()> {
     run(&["-l", "-c", ATLAMAL], "tests/expected/atlamal.txt.cl.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn atlamal_max_line_length() -> Result<()> {
+    run(&["-L", ATLAMAL], "tests/expected/atlamal.txt.max.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1() -> Result<()> {
+    run(&[LATIN1], "tests/expected/latin1.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_chars() -> Result<()> {
+    run(&["-m", LATIN1], "tests/expected/latin1.txt.m.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn atlamal_stdin() -> Result<()> {
@@ -218,3 +250,664 @@ fn test_all_words_lines() -> Result<()> {
 fn test_all_bytes_lines() -> Result<()> {
     run(&["-cl", EMPTY, FOX, ATLAMAL], "tests/expected/all.cl.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn test_all_lines_max_line_length() -> Result<()> {
+    run(&["-lL", EMPTY, FOX, ATLAMAL], "tests/expected/all.lmax.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_jobs() -> Result<()> {
+    run(&["-j", "2", EMPTY, FOX, ATLAMAL], "tests/expected/all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_keeps_order_and_skips_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["--jobs", "4", EMPTY, &bad, FOX, ATLAMAL])
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!("wcr: {bad}: No such file or directory\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_zero_jobs() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-j", "0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value '0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_total_always() -> Result<()> {
+    run(
+        &["--total=always", FOX],
+        "tests/expected/fox.txt.total_always.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_only() -> Result<()> {
+    run(
+        &["--total=only", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_only.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_total_never() -> Result<()> {
+    run(
+        &["--total=never", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.total_never.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_total() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--total=sometimes", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'sometimes'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_json() -> Result<()> {
+    run(
+        &["--output-format", "json", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_jsonl() -> Result<()> {
+    run(
+        &["--output-format", "jsonl", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.jsonl.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_csv() -> Result<()> {
+    run(
+        &["--output-format", "csv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_tsv() -> Result<()> {
+    run(
+        &["--output-format", "tsv", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn test_total_only_jsonl() -> Result<()> {
+    run(
+        &["--output-format=jsonl", "--total=only", "-wL", FOX, ATLAMAL],
+        "tests/expected/all.wmax.total_only.jsonl.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn json_records_bad_file() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--output-format=jsonl", "-l", &bad])
+        .assert()
+        .failure()
+        .stdout(format!(
+            r#"{{"type":"file","name":"{bad}","lines":null,"words":null,"bytes":null,"chars":null,"graphemes":null,"max_line_length":null,"blank":null,"comment":null,"code":null,"error":"No such file or directory"}}"#
+        ) + "\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_output_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--output-format=xml", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown format 'xml'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fox_fixed_width() -> Result<()> {
+    run(&["--fixed-width", FOX], "tests/expected/fox.txt.fixed.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn test_all_fixed_width() -> Result<()> {
+    run(
+        &["--fixed-width", EMPTY, FOX, ATLAMAL],
+        "tests/expected/all.fixed.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn atlamal_stdin_fixed_width() -> Result<()> {
+    let input = fs::read_to_string(ATLAMAL)?;
+    let expected = fs::read_to_string("tests/expected/atlamal.txt.stdin.fixed.out")?;
+
+    let output = Command::cargo_bin(PRG)?
+        .arg("--fixed-width")
+        .write_stdin(input)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn skips_dir() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args([DIR, FOX])
+        .assert()
+        .failure()
+        .stdout(format!(
+            "      0       0       0 {DIR}\n      1       9      48 {FOX}\n      1       9      48 total\n"
+        ))
+        .stderr(format!("wcr: {DIR}: Is a directory\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_recursive() -> Result<()> {
+    run(&["-r", DIR], "tests/expected/dir.r.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_recursive_include_exclude() -> Result<()> {
+    run(
+        &["--recursive", "--include=*.txt", "--exclude=skip", DIR],
+        "tests/expected/dir.r.include.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_include_without_recursive() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--include=*.txt", DIR])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--recursive"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from() -> Result<()> {
+    run(&["--files0-from", FILES0], "tests/expected/all.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin() -> Result<()> {
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .arg("--files0-from=-")
+        .write_stdin(fs::read(FILES0)?)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_files0_from_and_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", FILES0, FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_group_by_ext() -> Result<()> {
+    run(&["-r", "--group-by=ext", DIR], "tests/expected/dir.ext.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_group_by_dir_sort_bytes() -> Result<()> {
+    run(
+        &["-r", "--group-by", "dir", "--sort", "bytes", DIR],
+        "tests/expected/dir.dir.bytes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_group_by_depth_jsonl() -> Result<()> {
+    run(
+        &["-rl", "--group-by=depth:3", "--output-format=jsonl", DIR],
+        "tests/expected/dir.depth3.jsonl.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_group_by() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--group-by=depth:0", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown grouping 'depth:0'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn code_group_by_ext() -> Result<()> {
+    run(
+        &["-r", "--code", "--group-by=ext", CODE],
+        "tests/expected/code.ext.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn code_csv() -> Result<()> {
+    run(
+        &["-rl", "--code", "--output-format=csv", CODE],
+        "tests/expected/code.l.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn code_stdin() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .arg("--code")
+        .write_stdin("a\n\n# b\n")
+        .assert()
+        .success()
+        .stdout("      3       3       7       1       0       2\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn multilingual_unicode_words_graphemes() -> Result<()> {
+    run(
+        &["--words=unicode", "-mg", MULTILINGUAL],
+        "tests/expected/multilingual.txt.unicode.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_word_mode() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--words=spaces", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown value 'spaces'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn all_sort_bytes() -> Result<()> {
+    run(
+        &["--sort=bytes", EMPTY, FOX, ATLAMAL, LATIN1],
+        "tests/expected/all.sort.bytes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_reverse_filtered() -> Result<()> {
+    run(
+        &[
+            "-l",
+            "--sort=name",
+            "--reverse",
+            "--min-lines=1",
+            "--max-bytes=100",
+            "-r",
+            EMPTY,
+            FOX,
+            ATLAMAL,
+            LATIN1,
+            DIR,
+        ],
+        "tests/expected/sort.name.reverse.filtered.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_reverse_without_sort() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--reverse", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--sort <COLUMN>"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_sort_by_uncounted_column() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--sort=code", FOX])
+        .assert()
+        .failure()
+        .stderr("wcr: --sort by blank, comment or code lines needs --code\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stats() -> Result<()> {
+    run(
+        &["--stats", FOX, ATLAMAL, EMPTY],
+        "tests/expected/all.stats.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn stats_csv() -> Result<()> {
+    run(
+        &["-l", "--stats", "--output-format=csv", ATLAMAL, EMPTY],
+        "tests/expected/all.l.stats.csv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn histogram() -> Result<()> {
+    run(
+        &["-l", "--histogram", "-r", DIR, CODE],
+        "tests/expected/histogram.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn histogram_table_format() -> Result<()> {
+    run(
+        &[
+            "-l",
+            "--histogram",
+            "--output-format=table",
+            "-r",
+            DIR,
+            CODE,
+        ],
+        "tests/expected/histogram.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_histogram_with_records() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--histogram", "--output-format=json", FOX])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("wcr: --histogram prints only with --output-format=table\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn watch_stdin() -> Result<()> {
+    // Counted to the end of the input, before the first interval is up
+    let output = Command::cargo_bin(PRG)?
+        .args(["--watch", "--interval=60", "-lc"])
+        .write_stdin("one\ntwo\n")
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let snapshot: serde_json::Value = serde_json::from_str(stdout.trim_end())?;
+    assert_eq!(snapshot["name"], "-");
+    assert_eq!(snapshot["lines"], 2);
+    assert_eq!(snapshot["bytes"], 8);
+    assert!(snapshot["words"].is_null());
+    assert!(snapshot["lines_per_sec"].is_number());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn watch_follows_file() -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+
+    let path = std::env::temp_dir().join(format!("wcr-watch-{}", gen_bad_file()));
+    fs::write(&path, "one\n")?;
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["--watch", "--interval=0.05", "-l"])
+        .arg(&path)
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut counts = vec![];
+    let mut appended = false;
+    for line in lines.take(200) {
+        let snapshot: serde_json::Value = serde_json::from_str(&line?)?;
+        let count = snapshot["lines"].as_u64().unwrap();
+        if counts.last() != Some(&count) {
+            counts.push(count);
+        }
+        if count == 1 && !appended {
+            appended = true;
+            fs::OpenOptions::new()
+                .append(true)
+                .open(&path)?
+                .write_all(b"two\nthree\n")?;
+        }
+        if count == 3 {
+            break;
+        }
+    }
+    child.kill()?;
+    child.wait()?;
+    fs::remove_file(&path)?;
+
+    assert_eq!(counts, [1, 3]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn watch_file_runs_until_interrupted() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .args(["--watch", "--interval=0.05", "-l", FOX])
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+
+    // All of the file has been counted, yet it is still being followed
+    let first: serde_json::Value = serde_json::from_str(&lines.next().unwrap()?)?;
+    assert_eq!(first["lines"], 1);
+    lines.next().unwrap()?;
+    assert!(child.try_wait()?.is_none());
+
+    let killed = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+    assert!(killed.success());
+    // SIGINT is 2 on every Unix
+    assert_eq!(child.wait()?.signal(), Some(2));
+
+    // Every snapshot written before the interrupt is whole
+    for line in lines {
+        let snapshot: serde_json::Value = serde_json::from_str(&line?)?;
+        assert_eq!(snapshot["lines"], 1);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_watch_two_files() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--watch", FOX, ATLAMAL])
+        .assert()
+        .failure()
+        .stderr("wcr: --watch counts a single file or the standard input\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_watch_with_output_format() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--watch", "--output-format=json", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dir_record_has_counts_and_error() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--output-format=csv", DIR, &bad])
+        .assert()
+        .failure()
+        .stdout(format!(
+            "type,name,lines,words,bytes,chars,graphemes,max_line_length,blank,comment,code,error\n\
+             file,{DIR},0,,,,,,,,,Is a directory\n\
+             file,{bad},,,,,,,,,,No such file or directory\n\
+             total,,0,,,,,,,,,\n"
+        ))
+        .stderr(format!(
+            "wcr: {DIR}: Is a directory\nwcr: {bad}: No such file or directory\n"
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_files0_from() -> Result<()> {
+    let bad = gen_bad_file();
+    Command::cargo_bin(PRG)?
+        .args(["--files0-from", &bad])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(format!(
+            "wcr: cannot open '{bad}' for reading: No such file or directory\n"
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_stdin_rejects_dash() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(format!("-\0{FOX}\0"))
+        .assert()
+        .failure()
+        .stdout(format!("1 {FOX}\n"))
+        .stderr("wcr: no file name of '-' is allowed when reading names from stdin\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn files0_from_skips_empty_names() -> Result<()> {
+    // An empty name is reported, and the rest counted
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(format!("{FOX}\0\0{FOX}\0"))
+        .assert()
+        .failure()
+        .stdout(format!(" 1 {FOX}\n 1 {FOX}\n 2 total\n"))
+        .stderr("wcr: invalid zero-length file name\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(unix)]
+#[test]
+fn counts_non_utf8_names() -> Result<()> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    // Named on the command line, with --files0-from or found with -r, a
+    // file whose name is not UTF-8 is counted, and its name printed lossily
+    let dir = std::env::temp_dir().join(format!("wcr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    let path = dir.join(OsStr::from_bytes(b"caf\xe9.txt"));
+    fs::copy(FOX, &path)?;
+    let expected = format!("1 {}/caf\u{fffd}.txt\n", dir.display());
+
+    Command::cargo_bin(PRG)?
+        .arg("-l")
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    Command::cargo_bin(PRG)?
+        .args(["-l", "-r"])
+        .arg(&dir)
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    let mut names = path.as_os_str().as_bytes().to_vec();
+    names.push(0);
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--files0-from=-"])
+        .write_stdin(names)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+#!/usr/bin/env python3
+"""Says hello.
+
+# Not a comment
+"""
+
+GREETING = "Hello, # world"  # trailing
+
+
+def main():
+    # Print it
+    print(GREETING)
@@ -0,0 +1,11 @@
+//! Says hello.
+
+/* The greeting,
+   /* nested */ in full */
+const GREETING: &str = "Hello, // world";
+
+fn main() {
+    // Print it
+    println!("{}", GREETING); // and a newline
+    let quote = '"';
+}